@@ -131,15 +131,14 @@ if [ ! -f "./target/release/serialize_state" ]; then
     cargo build --release --bin serialize_state
 fi
 
-# Serialize CircleState
-circle_id_hex=$(openssl rand -hex 32)
+# Serialize CircleState (circle_id is derived from the funding outpoint in_utxo_0)
 contribution_per_round=100000
 round_duration=2592000
 created_at=$(date +%s)
 creator_pubkey_hex="023b709e70b6b30177f2e5fd05e43697f0870a4e942530ef19502f8cee07a63281"
 
 export circle_state_serialized=$(./target/release/serialize_state \
-    "${circle_id_hex}" \
+    "${in_utxo_0}" \
     "${contribution_per_round}" \
     "${round_duration}" \
     "${created_at}" \
@@ -161,11 +161,11 @@ export class CircleController {
             console.log("[JOIN CIRCLE] Found circle:", {
                 purpose: circle.purpose,
                 memberCount: circle.memberCount,
-                maxMembers: circle.totalRounds,
+                maxMembers: circle.maxMembers ?? circle.totalRounds,
             });
 
             // Check if circle is full
-            if (circle.memberCount >= circle.totalRounds) {
+            if (circle.memberCount >= (circle.maxMembers ?? circle.totalRounds)) {
                 throw new AppError("Circle is full", 400);
             }
 
@@ -4,6 +4,10 @@ import { StateService } from "../services/state.service";
 import { BitcoinService } from "../services/bitcoin.service";
 import { CircleService } from "../services/circle.service";
 import { AppError } from "../utils/errors";
+import {
+    joinCircleParameters,
+    contributeParameters,
+} from "../utils/spellParams";
 import crypto from "crypto";
 import { exec } from "child_process";
 import { promisify } from "util";
@@ -204,6 +208,7 @@ export class SpellController {
 
             // Handle state updates based on template type
             let prevTxs = providedPrevTxs;
+            let collateral = 0;
 
             console.log("[BUILD AND PROVE] templateName:", templateName);
             console.log("[BUILD AND PROVE] fundingUtxo:", fundingUtxo);
@@ -231,14 +236,20 @@ export class SpellController {
                         );
                     }
                 }
-                // Generate circle ID if not provided
-                const circleId =
-                    parameters.circle_id ||
-                    crypto.randomBytes(32).toString("hex");
+                // The contract requires circle_id = sha256(first spent outpoint)
+                if (!parameters.in_utxo_0) {
+                    throw new AppError(
+                        "in_utxo_0 is required to derive the circle ID",
+                        400
+                    );
+                }
+                const circleId = this.stateService.deriveCircleId(
+                    parameters.in_utxo_0
+                );
 
                 // Create initial circle state
                 const initialState = await this.stateService.createCircleState({
-                    circleId,
+                    circleId: parameters.in_utxo_0,
                     contributionPerRound: parseInt(
                         parameters.contribution_per_round || "0"
                     ),
@@ -247,6 +258,12 @@ export class SpellController {
                         parseInt(parameters.created_at_timestamp) ||
                         Math.floor(Date.now() / 1000),
                     creatorPubkey: parameters.creator_pubkey_hex || "",
+                    minMembers: parameters.min_members
+                        ? parseInt(parameters.min_members)
+                        : undefined,
+                    maxMembers: parameters.max_members
+                        ? parseInt(parameters.max_members)
+                        : undefined,
                 });
 
                 // Update parameters with generated state
@@ -354,15 +371,38 @@ export class SpellController {
                     }
                 }
 
+                // The spell's public input, witness action and state update must
+                // all carry the same values
+                const joinedAt =
+                    parseInt(parameters.joined_at_timestamp) ||
+                    Math.floor(Date.now() / 1000);
+                const payoutRound = parseInt(parameters.payout_round || "0");
+                parameters.joined_at_timestamp = String(joinedAt);
+                parameters.payout_round = String(payoutRound);
+
                 // Update state with new member
-                const updatedState = await this.stateService.addMember({
-                    prevState,
-                    newMemberPubkey: parameters.new_member_pubkey_hex || "",
-                    payoutRound: parseInt(parameters.payout_round || "0"),
-                    joinedAt:
-                        parseInt(parameters.joined_at_timestamp) ||
-                        Math.floor(Date.now() / 1000),
-                });
+                const { stateHex: updatedState, signingHash } =
+                    await this.stateService.addMember({
+                        prevState,
+                        newMemberPubkey: parameters.new_member_pubkey_hex || "",
+                        payoutRound,
+                        joinedAt,
+                    });
+
+                // Witness and output parameters: the signed Join action and the
+                // circle UTXO grown by the new member's collateral
+                collateral = this.stateService.getCollateralPerMember(prevState);
+                Object.assign(
+                    parameters,
+                    joinCircleParameters({
+                        newMemberPubkeyHex: parameters.new_member_pubkey_hex,
+                        signatureHex: parameters.new_member_signature_hex,
+                        signingHash,
+                        circleValue: await this.getCircleValue(parameters),
+                        collateral,
+                        collateralUtxo: parameters.collateral_utxo,
+                    })
+                );
 
                 // Update parameters with new state
                 parameters.updated_circle_state_data = updatedState;
@@ -401,6 +441,19 @@ export class SpellController {
                             await this.bitcoinService.getTransaction(circleTxid);
                         prevTxs = circleTx;
                         console.log("[JOIN CIRCLE] Fetched circle transaction, length:", circleTx?.length || 0);
+
+                        if (collateral > 0) {
+                            const { txid: collateralTxid } =
+                                this.bitcoinService.parseUtxo(
+                                    parameters.collateral_utxo
+                                );
+                            const collateralTx =
+                                await this.bitcoinService.getTransaction(
+                                    collateralTxid
+                                );
+                            // Join transactions with comma (as required by charms spell check)
+                            prevTxs = `${circleTx},${collateralTx}`;
+                        }
                     } catch (error) {
                         console.warn(
                             `Failed to fetch circle transaction: ${error}. Continuing without it.`
@@ -420,18 +473,34 @@ export class SpellController {
                     );
                 }
 
+                const timestamp =
+                    parseInt(parameters.contribution_timestamp) ||
+                    Math.floor(Date.now() / 1000);
+                parameters.contribution_timestamp = String(timestamp);
+
                 // Record contribution
-                const updatedState = await this.stateService.recordContribution(
-                    {
+                const { stateHex: updatedState, signingHash } =
+                    await this.stateService.recordContribution({
                         prevState,
                         contributorPubkey:
                             parameters.contributor_pubkey_hex || "",
                         amount: parseInt(parameters.contribution_amount || "0"),
-                        timestamp:
-                            parseInt(parameters.contribution_timestamp) ||
-                            Math.floor(Date.now() / 1000),
+                        timestamp,
                         txid: parameters.txid_hex || "",
-                    }
+                    });
+
+                // Witness and output parameters: the signed Contribute action and
+                // the circle UTXO grown by the contribution
+                Object.assign(
+                    parameters,
+                    contributeParameters({
+                        contributorPubkeyHex: parameters.contributor_pubkey_hex,
+                        txidHex: parameters.txid_hex,
+                        signatureHex: parameters.contributor_signature_hex,
+                        signingHash,
+                        circleValue: await this.getCircleValue(parameters),
+                        amount: parseInt(parameters.contribution_amount || "0"),
+                    })
                 );
 
                 // Update parameters with new state
@@ -478,7 +547,11 @@ export class SpellController {
 
             const spellYaml = await this.spellService.buildSpellFromTemplate(
                 templateName,
-                parameters
+                parameters,
+                // A circle without collateral takes no collateral input
+                templateName === "join-circle" && collateral === 0
+                    ? ["collateral_utxo"]
+                    : []
             );
 
             // Generate PSBT
@@ -553,6 +626,8 @@ export class SpellController {
                             roundStartedAt: parseInt(parameters.created_at_timestamp) || Math.floor(Date.now() / 1000),
                             roundDuration: parseInt(parameters.round_duration || "0"),
                             currentPayoutIndex: 0,
+                            minMembers: parameters.min_members ? parseInt(parameters.min_members) : 2,
+                            maxMembers: parameters.max_members ? parseInt(parameters.max_members) : 12,
                             members: [{
                                 pubkey: parameters.creator_pubkey_hex || "",
                                 hasReceivedPayout: false,
@@ -611,4 +686,26 @@ export class SpellController {
             next(error);
         }
     }
+
+    /**
+     * Value of the circle UTXO a spell spends: circle_utxo_value if provided,
+     * otherwise looked up from circle_utxo
+     */
+    private async getCircleValue(
+        parameters: Record<string, string>
+    ): Promise<number> {
+        if (parameters.circle_utxo_value) {
+            return parseInt(parameters.circle_utxo_value);
+        }
+        if (!parameters.circle_utxo) {
+            throw new AppError(
+                "circle_utxo or circle_utxo_value is required to compute circle_amount",
+                400
+            );
+        }
+        const { txid, vout } = this.bitcoinService.parseUtxo(
+            parameters.circle_utxo
+        );
+        return this.bitcoinService.getUtxoValue(txid, vout);
+    }
 }
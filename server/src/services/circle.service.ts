@@ -20,6 +20,8 @@ interface CircleInfo {
     roundStartedAt: number;
     roundDuration: number; // seconds
     currentPayoutIndex: number;
+    minMembers?: number; // members needed to start the circle
+    maxMembers?: number; // member cap
     members: Array<{
         pubkey: string; // hex-encoded
         hasReceivedPayout: boolean;
@@ -53,6 +55,8 @@ interface CircleStateData {
     created_at: number;
     round_started_at: number;
     round_duration: number;
+    min_members: number;
+    max_members: number;
     is_complete: boolean;
     prev_state_hash: number[]; // [u8; 32]
 }
@@ -409,6 +413,8 @@ export class CircleService {
                 roundStartedAt: state.round_started_at,
                 roundDuration: state.round_duration,
                 currentPayoutIndex: state.current_payout_index,
+                minMembers: state.min_members,
+                maxMembers: state.max_members,
                 members: state.members.map((m) => ({
                     pubkey: Buffer.from(m.pubkey).toString("hex"),
                     hasReceivedPayout: m.has_received_payout,
@@ -44,10 +44,13 @@ export class SpellService {
 
     /**
      * Build spell from template with parameter substitution
+     * @param omitInputs Variables of optional `ins` entries (e.g. collateral_utxo)
+     * to drop from the template instead of substituting
      */
     async buildSpellFromTemplate(
         templateName: string,
-        parameters: Record<string, string>
+        parameters: Record<string, string>,
+        omitInputs: string[] = []
     ): Promise<string> {
         const templatePath = join(this.spellsDir, `${templateName}.yaml`);
 
@@ -55,34 +58,51 @@ export class SpellService {
             throw new AppError(`Template not found: ${templateName}.yaml`, 404);
         }
 
+        let template: string;
         try {
             // Read template
-            let template = await readFile(templatePath, "utf-8");
-
-            // Substitute parameters
-            // Replace ${variable} with parameter value
-            for (const [key, value] of Object.entries(parameters)) {
-                const regex = new RegExp(`\\$\\{${key}\\}`, "g");
-                template = template.replace(regex, value);
-            }
-
-            // Check for unresolved variables (optional - can be removed if you want to allow partial substitution)
-            const unresolvedVars = template.match(/\$\{[^}]+\}/g);
-            if (unresolvedVars && unresolvedVars.length > 0) {
-                console.warn(
-                    `Warning: Unresolved variables found: ${unresolvedVars.join(
-                        ", "
-                    )}`
-                );
-            }
-
-            return template;
+            template = await readFile(templatePath, "utf-8");
         } catch (error: any) {
             throw new AppError(
                 `Failed to build spell from template: ${error.message}`,
                 500
             );
         }
+
+        // Drop omitted inputs: the `- utxo_id: ${variable}` line and the
+        // more indented lines under it
+        for (const variable of omitInputs) {
+            const input = new RegExp(
+                `^([ \\t]*)- utxo_id: \\$\\{${variable}\\}\\n(?:\\1 .*\\n)*`,
+                "m"
+            );
+            template = template.replace(input, "");
+        }
+
+        // Substitute parameters
+        // Replace ${variable} with parameter value
+        for (const [key, value] of Object.entries(parameters)) {
+            const regex = new RegExp(`\\$\\{${key}\\}`, "g");
+            template = template.replace(regex, () => value);
+        }
+
+        // Every variable outside comments must be resolved, or the spell would
+        // carry literal placeholders
+        const unresolvedVars = template
+            .split("\n")
+            .map((line) => line.replace(/(^|\s)#.*$/, ""))
+            .join("\n")
+            .match(/\$\{[^}]+\}/g);
+        if (unresolvedVars && unresolvedVars.length > 0) {
+            throw new AppError(
+                `Unresolved variables in ${templateName}.yaml: ${[
+                    ...new Set(unresolvedVars),
+                ].join(", ")}`,
+                400
+            );
+        }
+
+        return template;
     }
 
     /**
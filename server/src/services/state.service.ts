@@ -1,10 +1,42 @@
 import { exec } from "child_process";
 import { promisify } from "util";
 import { join } from "path";
+import crypto from "crypto";
+import cbor from "cbor";
 import { AppError } from "../utils/errors";
 
 const execAsync = promisify(exec);
 
+/**
+ * An updated circle state and the hash the acting member signs for the witness
+ * (CircleState::signing_hash of the action on the previous state)
+ */
+export interface StateUpdate {
+    stateHex: string;
+    signingHash: string;
+}
+
+/**
+ * Split update_state output into the new state (stdout) and the signing hash it
+ * reports on stderr
+ */
+function parseStateUpdate(
+    command: string,
+    stdout: string,
+    stderr: string
+): StateUpdate {
+    const stateHex = stdout.trim();
+    const signingHash = stderr.match(/Signing hash: ([0-9a-f]{64})/)?.[1];
+
+    if (!stateHex || !signingHash) {
+        throw new Error(
+            `update_state ${command} returned no state or signing hash. stderr: ${stderr}`
+        );
+    }
+
+    return { stateHex, signingHash };
+}
+
 export class StateService {
     private readonly projectRoot: string;
 
@@ -13,20 +45,46 @@ export class StateService {
         this.projectRoot = join(process.cwd(), "..");
     }
 
+    /**
+     * Derive the circle ID the contract expects from the funding outpoint:
+     * sha256(txid in internal byte order || vout as u32 little-endian)
+     * @param fundingUtxo Outpoint in "txid:vout" format
+     * @returns Hex-encoded 32-byte circle ID
+     */
+    deriveCircleId(fundingUtxo: string): string {
+        const [txid, vout] = fundingUtxo.split(":");
+        const outpoint = Buffer.alloc(36);
+        Buffer.from(txid, "hex").reverse().copy(outpoint, 0);
+        outpoint.writeUInt32LE(parseInt(vout, 10), 32);
+        return crypto.createHash("sha256").update(outpoint).digest("hex");
+    }
+
     /**
      * Create initial circle state using serialize_state binary
      * @param params Circle creation parameters
      * @returns Hex-encoded CBOR serialized state
      */
     async createCircleState(params: {
-        circleId: string; // hex-encoded 32-byte array
+        circleId: string; // funding outpoint "txid:vout" for new circles, or hex-encoded 32-byte id of an existing circle
         contributionPerRound: number; // satoshis
         roundDuration: number; // seconds
         createdAt: number; // Unix timestamp
         creatorPubkey: string; // hex-encoded public key
+        minMembers?: number; // members needed to start (serialize_state default: 2)
+        maxMembers?: number; // member cap (serialize_state default: 12)
     }): Promise<string> {
         try {
-            const command = `./target/release/serialize_state "${params.circleId}" ${params.contributionPerRound} ${params.roundDuration} ${params.createdAt} "${params.creatorPubkey}"`;
+            let command = `./target/release/serialize_state "${params.circleId}" ${params.contributionPerRound} ${params.roundDuration} ${params.createdAt} "${params.creatorPubkey}"`;
+            if (!params.circleId.includes(":")) {
+                // An existing circle's id: rebuild its genesis state
+                command += " --rebuild";
+            }
+            if (params.minMembers) {
+                command += ` --min-members=${params.minMembers}`;
+            }
+            if (params.maxMembers) {
+                command += ` --max-members=${params.maxMembers}`;
+            }
 
             const { stdout, stderr } = await execAsync(command, {
                 cwd: this.projectRoot,
@@ -73,17 +131,19 @@ export class StateService {
                 roundDuration: circle.roundDuration,
                 createdAt: circle.createdAt,
                 creatorPubkey: creator.pubkey,
+                minMembers: circle.minMembers,
+                maxMembers: circle.maxMembers,
             });
 
             // Add each additional member
             for (let i = 1; i < circle.members.length; i++) {
                 const member = circle.members[i];
-                stateHex = await this.addMember({
+                ({ stateHex } = await this.addMember({
                     prevState: stateHex,
                     newMemberPubkey: member.pubkey,
                     payoutRound: member.payoutRound,
                     joinedAt: circle.createdAt, // Use circle creation time as fallback
-                });
+                }));
             }
 
             return stateHex;
@@ -99,14 +159,15 @@ export class StateService {
     /**
      * Add member to circle state using update_state binary
      * @param params Member addition parameters
-     * @returns Hex-encoded CBOR serialized updated state
+     * @returns Hex-encoded CBOR serialized updated state and the hash the new
+     * member signs
      */
     async addMember(params: {
         prevState: string; // hex-encoded previous state
         newMemberPubkey: string; // hex-encoded public key
         payoutRound: number; // round number (0-indexed)
         joinedAt: number; // Unix timestamp
-    }): Promise<string> {
+    }): Promise<StateUpdate> {
         try {
             const command = `./target/release/update_state add_member "${params.prevState}" "${params.newMemberPubkey}" ${params.payoutRound} ${params.joinedAt}`;
 
@@ -115,15 +176,7 @@ export class StateService {
                 maxBuffer: 10 * 1024 * 1024,
             });
 
-            const updatedStateHex = stdout.trim();
-
-            if (!updatedStateHex || updatedStateHex.length === 0) {
-                throw new Error(
-                    `update_state add_member returned empty output. stderr: ${stderr}`
-                );
-            }
-
-            return updatedStateHex;
+            return parseStateUpdate("add_member", stdout, stderr);
         } catch (error: any) {
             const errorMessage = error.stderr || error.stdout || error.message;
             throw new AppError(
@@ -136,7 +189,8 @@ export class StateService {
     /**
      * Record contribution using update_state binary
      * @param params Contribution parameters
-     * @returns Hex-encoded CBOR serialized updated state
+     * @returns Hex-encoded CBOR serialized updated state and the hash the
+     * contributor signs
      */
     async recordContribution(params: {
         prevState: string; // hex-encoded previous state
@@ -144,7 +198,7 @@ export class StateService {
         amount: number; // satoshis
         timestamp: number; // Unix timestamp
         txid: string; // hex-encoded transaction ID
-    }): Promise<string> {
+    }): Promise<StateUpdate> {
         try {
             const command = `./target/release/update_state record_contribution "${params.prevState}" "${params.contributorPubkey}" ${params.amount} ${params.timestamp} "${params.txid}"`;
 
@@ -153,15 +207,7 @@ export class StateService {
                 maxBuffer: 10 * 1024 * 1024,
             });
 
-            const updatedStateHex = stdout.trim();
-
-            if (!updatedStateHex || updatedStateHex.length === 0) {
-                throw new Error(
-                    `update_state record_contribution returned empty output. stderr: ${stderr}`
-                );
-            }
-
-            return updatedStateHex;
+            return parseStateUpdate("record_contribution", stdout, stderr);
         } catch (error: any) {
             const errorMessage = error.stderr || error.stdout || error.message;
             throw new AppError(
@@ -217,11 +263,30 @@ export class StateService {
         payoutRound: number,
         joinedAt: number
     ): Promise<string> {
-        return this.addMember({
+        const { stateHex } = await this.addMember({
             prevState,
             newMemberPubkey,
             payoutRound,
             joinedAt,
         });
+        return stateHex;
+    }
+
+    /**
+     * Collateral each member locks in the circle, read from a serialized state
+     * (states encoded before collateral existed require none)
+     * @param stateHex Hex-encoded CBOR serialized state
+     * @returns Collateral per member in satoshis
+     */
+    getCollateralPerMember(stateHex: string): number {
+        try {
+            const state = cbor.decode(Buffer.from(stateHex, "hex"));
+            return Number(state.collateral_per_member ?? 0);
+        } catch (error: any) {
+            throw new AppError(
+                `Failed to decode circle state: ${error.message}`,
+                400
+            );
+        }
     }
 }
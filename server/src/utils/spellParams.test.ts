@@ -0,0 +1,122 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { SpellService } from "../services/spell.service";
+import { joinCircleParameters, contributeParameters } from "./spellParams";
+
+const PUBKEY = "02" + "3b".repeat(32);
+const SIGNATURE = "11".repeat(64);
+const SIGNING_HASH = "ab".repeat(32);
+const TXID = "cd".repeat(32);
+
+// Variables left outside YAML comments
+function unresolved(spell: string): string[] {
+    return (
+        spell
+            .split("\n")
+            .map((line) => line.replace(/(^|\s)#.*$/, ""))
+            .join("\n")
+            .match(/\$\{[^}]+\}/g) ?? []
+    );
+}
+
+function joinParameters(collateral: number): Record<string, string> {
+    return {
+        app_id: "aa".repeat(32),
+        app_vk: "bb".repeat(32),
+        joined_at_timestamp: "1700000000",
+        payout_round: "1",
+        circle_utxo: `${TXID}:0`,
+        circle_address: "tb1qcircle",
+        prev_circle_state_data: "a0",
+        updated_circle_state_data: "a1",
+        new_member_pubkey_hex: PUBKEY,
+        ...joinCircleParameters({
+            newMemberPubkeyHex: PUBKEY,
+            signatureHex: SIGNATURE,
+            signingHash: SIGNING_HASH,
+            circleValue: 1000,
+            collateral,
+            collateralUtxo: collateral > 0 ? `${TXID}:1` : undefined,
+        }),
+    };
+}
+
+test("join-circle spell carries the signed action and collateral", async () => {
+    const spell = await new SpellService().buildSpellFromTemplate(
+        "join-circle",
+        joinParameters(500)
+    );
+
+    assert.deepEqual(unresolved(spell), []);
+    assert.match(spell, /pubkey: \[2, 59, 59,/);
+    assert.match(spell, /- \[17, 17,/);
+    assert.match(spell, new RegExp(`- utxo_id: ${TXID}:1`));
+    assert.match(spell, /amount: 1500\n/);
+});
+
+test("join-circle spell drops the collateral input when none is required", async () => {
+    const spell = await new SpellService().buildSpellFromTemplate(
+        "join-circle",
+        joinParameters(0),
+        ["collateral_utxo"]
+    );
+
+    assert.deepEqual(unresolved(spell), []);
+    assert.equal(spell.match(/- utxo_id:/g)?.length, 1);
+    assert.match(spell, /amount: 1000\n/);
+});
+
+test("contribute spell carries the signed action", async () => {
+    const spell = await new SpellService().buildSpellFromTemplate(
+        "contribute",
+        {
+            app_id: "aa".repeat(32),
+            app_vk: "bb".repeat(32),
+            contribution_timestamp: "1700000000",
+            contribution_amount: "250",
+            contribution_utxo: `${TXID}:2`,
+            circle_utxo: `${TXID}:0`,
+            circle_address: "tb1qcircle",
+            contributor_address: "tb1qcontributor",
+            prev_circle_state_data: "a0",
+            updated_circle_state_data: "a1",
+            nft_ticker: "SEALED_SCROLL",
+            ...contributeParameters({
+                contributorPubkeyHex: PUBKEY,
+                txidHex: TXID,
+                signatureHex: SIGNATURE,
+                signingHash: SIGNING_HASH,
+                circleValue: 1000,
+                amount: 250,
+            }),
+        }
+    );
+
+    assert.deepEqual(unresolved(spell), []);
+    assert.match(spell, /txid: \[205, 205,/);
+    assert.match(spell, /amount: 1250\n/);
+});
+
+test("unresolved spell variables are rejected", async () => {
+    const parameters = joinParameters(500);
+    delete parameters.circle_amount;
+
+    await assert.rejects(
+        new SpellService().buildSpellFromTemplate("join-circle", parameters),
+        /Unresolved variables in join-circle\.yaml: \$\{circle_amount\}/
+    );
+});
+
+test("a missing signature names the hash to sign", () => {
+    assert.throws(
+        () =>
+            contributeParameters({
+                contributorPubkeyHex: PUBKEY,
+                txidHex: TXID,
+                signingHash: SIGNING_HASH,
+                circleValue: 1000,
+                amount: 250,
+            }),
+        new RegExp(`contributor must sign ${SIGNING_HASH}`)
+    );
+});
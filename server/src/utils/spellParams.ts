@@ -0,0 +1,107 @@
+import { AppError } from "./errors";
+
+/**
+ * Render hex-encoded bytes as the list of byte values the spell YAML expects
+ * (e.g. "[2, 59, 112]")
+ * @param hex Hex-encoded bytes
+ * @param length Expected number of bytes
+ * @param name Parameter name, for error messages
+ */
+export function toByteList(hex: string, length: number, name: string): string {
+    if (!hex || !new RegExp(`^[0-9a-fA-F]{${length * 2}}$`).test(hex)) {
+        throw new AppError(
+            `${name} must be ${length * 2} hex characters (${length} bytes)`,
+            400
+        );
+    }
+    return `[${Array.from(Buffer.from(hex, "hex")).join(", ")}]`;
+}
+
+/**
+ * Signature the acting member made over the action's signing hash; a request
+ * without one is told which hash to sign
+ */
+function signatureByteList(
+    signatureHex: string | undefined,
+    name: string,
+    signer: string,
+    signingHash: string
+): string {
+    if (!signatureHex) {
+        throw new AppError(
+            `${name} is required: the ${signer} must sign ${signingHash}`,
+            400
+        );
+    }
+    return toByteList(signatureHex, 64, name);
+}
+
+/**
+ * Witness and output parameters of join-circle.yaml
+ * @returns Parameters to merge into the spell parameters
+ */
+export function joinCircleParameters(params: {
+    newMemberPubkeyHex: string;
+    signatureHex?: string; // 64-byte compact signature over signingHash
+    signingHash: string;
+    circleValue: number; // satoshis held by the circle UTXO being spent
+    collateral: number; // the circle's collateral_per_member
+    collateralUtxo?: string;
+}): Record<string, string> {
+    if (params.collateral > 0 && !params.collateralUtxo) {
+        throw new AppError(
+            `collateral_utxo is required: the circle locks ${params.collateral} sats of collateral per member`,
+            400
+        );
+    }
+
+    return {
+        new_member_pubkey: params.newMemberPubkeyHex,
+        new_member_pubkey_bytes: toByteList(
+            params.newMemberPubkeyHex,
+            33,
+            "new_member_pubkey_hex"
+        ),
+        new_member_signature_bytes: signatureByteList(
+            params.signatureHex,
+            "new_member_signature_hex",
+            "new member",
+            params.signingHash
+        ),
+        // The circle UTXO grows by exactly the locked bond
+        circle_amount: String(params.circleValue + params.collateral),
+        ...(params.collateral > 0
+            ? { collateral_utxo: params.collateralUtxo as string }
+            : {}),
+    };
+}
+
+/**
+ * Witness and output parameters of contribute.yaml
+ * @returns Parameters to merge into the spell parameters
+ */
+export function contributeParameters(params: {
+    contributorPubkeyHex: string;
+    txidHex: string;
+    signatureHex?: string; // 64-byte compact signature over signingHash
+    signingHash: string;
+    circleValue: number; // satoshis held by the circle UTXO being spent
+    amount: number; // satoshis contributed
+}): Record<string, string> {
+    return {
+        contributor_pubkey_bytes: toByteList(
+            params.contributorPubkeyHex,
+            33,
+            "contributor_pubkey_hex"
+        ),
+        txid_bytes: toByteList(params.txidHex, 32, "txid_hex"),
+        contributor_signature_bytes: signatureByteList(
+            params.signatureHex,
+            "contributor_signature_hex",
+            "contributor",
+            params.signingHash
+        ),
+        // The circle UTXO grows by exactly the contribution
+        circle_amount: String(params.circleValue + params.amount),
+    };
+}
@@ -1,7 +1,7 @@
 #[cfg(not(target_arch = "wasm32"))]
-use charmcircle::{CircleState, PubKey};
+use charmcircle::{CircleFee, CircleState, DefaultPolicy, FeeRate, PayoutPolicy, PubKey};
 #[cfg(not(target_arch = "wasm32"))]
-use ciborium;
+use charms_sdk::data::UtxoId;
 #[cfg(not(target_arch = "wasm32"))]
 use std::env;
 
@@ -10,21 +10,26 @@ fn main() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 6 {
-        eprintln!("Usage: serialize_state <circle_id_hex> <contribution_per_round> <round_duration> <created_at_timestamp> <creator_pubkey_hex>");
-        eprintln!("Example: serialize_state $(openssl rand -hex 32) 100000 2592000 $(date +%s) 023b709e70b6b30177f2e5fd05e43697f0870a4e942530ef19502f8cee07a63281");
+        eprintln!("Usage: serialize_state <funding_utxo> <contribution_per_round> <round_duration> <created_at_timestamp> <creator_pubkey_hex> [options]");
+        eprintln!("  funding_utxo: txid:vout of the first input spent by the create-circle spell;");
+        eprintln!("                the circle_id is derived from it");
+        eprintln!("Options:");
+        eprintln!("  --rebuild  take a 64-hex circle_id instead of funding_utxo, to rebuild the genesis state of an existing circle");
+        eprintln!("  --payout-policy=<fixed|lottery|seniority|auction>  (default: fixed)");
+        eprintln!(
+            "  --default-policy=<reduced-pot|skip-payout|collateral>  (default: reduced-pot)"
+        );
+        eprintln!("  --min-members=<n>  members needed to start the circle (default: 2)");
+        eprintln!("  --max-members=<n>  most members the circle accepts (default: 12)");
+        eprintln!("  --collateral=<sats>  refundable bond locked by each member on joining, including the creator (default: 0)");
+        eprintln!("  --grace-period=<seconds>  late contributions accepted after each round deadline (default: 0)");
+        eprintln!("  --fee-bps=<n> | --fee-sats=<sats>  fee deducted from each payout, in basis points of the pot (rounded down) or flat");
+        eprintln!("  --fee-recipient=<pubkey_hex>  key paid the fee (required with a fee)");
+        eprintln!("  --cancel-quorum=<n>  member approvals needed to cancel the circle (default: 0 = all members)");
+        eprintln!("Example: serialize_state 92077a14998b31367efeec5203a00f1080facdb270cbf055f09b66ae0a273c7d:0 100000 2592000 $(date +%s) 023b709e70b6b30177f2e5fd05e43697f0870a4e942530ef19502f8cee07a63281");
         std::process::exit(1);
     }
 
-    // Parse circle_id (64 hex chars = 32 bytes)
-    let circle_id_hex = &args[1];
-    if circle_id_hex.len() != 64 {
-        eprintln!("Error: circle_id must be 64 hex characters (32 bytes)");
-        std::process::exit(1);
-    }
-    let circle_id = hex::decode(circle_id_hex).expect("Invalid hex for circle_id");
-    let mut circle_id_bytes = [0u8; 32];
-    circle_id_bytes.copy_from_slice(&circle_id);
-
     // Parse contribution_per_round (satoshis)
     let contribution_per_round: u64 = args[2].parse().expect("Invalid contribution_per_round");
 
@@ -44,18 +49,140 @@ fn main() {
         hex::decode(creator_pubkey_hex).expect("Invalid hex for creator_pubkey");
     let creator_pubkey = PubKey(creator_pubkey_bytes);
 
-    // Create circle state
-    let mut circle_state = CircleState::new(
-        circle_id_bytes,
-        contribution_per_round,
-        round_duration,
-        created_at,
-    );
+    let rebuild = args[6..].iter().any(|option| option == "--rebuild");
+
+    // Parse optional settings
+    let mut payout_policy = PayoutPolicy::Fixed;
+    let mut default_policy = DefaultPolicy::ReducedPot;
+    let mut min_members: u32 = 2;
+    let mut max_members: u32 = 12;
+    let mut grace_period: u64 = 0;
+    let mut collateral_per_member: u64 = 0;
+    let mut fee_rate: Option<FeeRate> = None;
+    let mut fee_recipient: Option<PubKey> = None;
+    let mut cancel_quorum: u32 = 0;
+    for option in &args[6..] {
+        let (key, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        match key {
+            "--rebuild" => {}
+            "--payout-policy" => {
+                payout_policy = match value {
+                    "fixed" => PayoutPolicy::Fixed,
+                    "lottery" => PayoutPolicy::Lottery,
+                    "seniority" => PayoutPolicy::Seniority,
+                    "auction" => PayoutPolicy::Auction,
+                    _ => {
+                        eprintln!("Error: unknown payout policy: {}", value);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--default-policy" => {
+                default_policy = match value {
+                    "reduced-pot" => DefaultPolicy::ReducedPot,
+                    "skip-payout" => DefaultPolicy::SkipPayout,
+                    "collateral" => DefaultPolicy::Collateral,
+                    _ => {
+                        eprintln!("Error: unknown default policy: {}", value);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--min-members" => min_members = value.parse().expect("Invalid min-members"),
+            "--max-members" => max_members = value.parse().expect("Invalid max-members"),
+            "--collateral" => collateral_per_member = value.parse().expect("Invalid collateral"),
+            "--grace-period" => grace_period = value.parse().expect("Invalid grace-period"),
+            "--fee-bps" => {
+                fee_rate = Some(FeeRate::BasisPoints(
+                    value.parse().expect("Invalid fee-bps"),
+                ))
+            }
+            "--fee-sats" => {
+                fee_rate = Some(FeeRate::Flat(value.parse().expect("Invalid fee-sats")))
+            }
+            "--cancel-quorum" => cancel_quorum = value.parse().expect("Invalid cancel-quorum"),
+            "--fee-recipient" => {
+                fee_recipient = Some(PubKey(
+                    hex::decode(value).expect("Invalid hex for fee-recipient"),
+                ))
+            }
+            _ => {
+                eprintln!("Error: unknown option: {}", option);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // Add creator as first member (payout_round 0)
-    circle_state
-        .add_member(creator_pubkey, 0, created_at)
-        .expect("Failed to add creator as member");
+    // Create circle state: new circles through CircleState::create, which
+    // derives circle_id from the funding outpoint; --rebuild takes an existing
+    // circle_id (64 hex chars = 32 bytes)
+    let circle_arg = &args[1];
+    let created = if rebuild {
+        if circle_arg.len() != 64 {
+            eprintln!("Error: circle_id must be 64 hex characters (32 bytes)");
+            std::process::exit(1);
+        }
+        let circle_id = hex::decode(circle_arg).expect("Invalid hex for circle_id");
+        let mut circle_id_bytes = [0u8; 32];
+        circle_id_bytes.copy_from_slice(&circle_id);
+        let mut state = CircleState::new(
+            circle_id_bytes,
+            contribution_per_round,
+            round_duration,
+            created_at,
+            min_members,
+            max_members,
+        );
+        state
+            .validate_member_limits()
+            .and_then(|_| state.add_member(creator_pubkey, 0, created_at))
+            .map(|_| state)
+    } else {
+        let funding_utxo = match UtxoId::from_str(circle_arg) {
+            Ok(funding_utxo) => funding_utxo,
+            Err(_) => {
+                eprintln!("Error: funding_utxo must be txid:vout (use --rebuild for a circle_id)");
+                std::process::exit(1);
+            }
+        };
+        CircleState::create(
+            &funding_utxo,
+            contribution_per_round,
+            round_duration,
+            created_at,
+            min_members,
+            max_members,
+            creator_pubkey,
+        )
+    };
+    let mut circle_state = match created {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    circle_state.payout_policy = payout_policy;
+    circle_state.default_policy = default_policy;
+    circle_state.grace_period = grace_period;
+    circle_state.collateral_per_member = collateral_per_member;
+    circle_state.cancel_quorum = cancel_quorum;
+    circle_state.fee = match (fee_rate, fee_recipient) {
+        (Some(rate), Some(recipient)) => Some(CircleFee { rate, recipient }),
+        (None, None) => None,
+        _ => {
+            eprintln!("Error: --fee-bps/--fee-sats and --fee-recipient must be given together");
+            std::process::exit(1);
+        }
+    };
+    // The creator joined before the settings were applied: lock their bond too
+    circle_state.members[0].collateral = collateral_per_member;
+
+    if let Err(e) = circle_state.validate() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 
     // Serialize using ciborium (same as Charms SDK uses internally)
     let mut serialized = Vec::new();
@@ -48,6 +48,8 @@ fn main() {
         contribution_per_round,
         round_duration,
         created_at,
+        2,
+        12,
     );
 
     // Add creator as first member (payout_round 0)
@@ -1,7 +1,7 @@
 #[cfg(not(target_arch = "wasm32"))]
-use charmcircle::{CircleState, PubKey};
+use charmcircle::{CircleAction, CircleState, PubKey, RenewalOrder};
 #[cfg(not(target_arch = "wasm32"))]
-use ciborium;
+use charms_sdk::data::UtxoId;
 #[cfg(not(target_arch = "wasm32"))]
 use std::env;
 
@@ -12,8 +12,16 @@ fn main() {
     if args.len() < 2 {
         eprintln!("Usage: update_state <command> [args...]");
         eprintln!("Commands:");
-        eprintln!("  add_member <prev_state_hex> <new_member_pubkey_hex> <payout_round> <joined_at_timestamp>");
+        eprintln!("  add_member <prev_state_hex> <new_member_pubkey_hex> <payout_round> <joined_at_timestamp> [collateral_sats]");
+        eprintln!("  start_circle <prev_state_hex> <creator_pubkey_hex> <timestamp>");
         eprintln!("  record_contribution <prev_state_hex> <contributor_pubkey_hex> <amount> <timestamp> <txid_hex>");
+        eprintln!("  prepay <prev_state_hex> <member_pubkey_hex> <amount> <timestamp> <txid_hex>");
+        eprintln!("  mark_defaults <prev_state_hex> <timestamp> <approver_pubkey_hex...>");
+        eprintln!("  remove_member <prev_state_hex> <member_pubkey_hex>");
+        eprintln!("  transfer_seat <prev_state_hex> <from_pubkey_hex> <to_pubkey_hex>");
+        eprintln!("  cancel <prev_state_hex> <approver_pubkey_hex...>");
+        eprintln!("  settle <prev_state_hex> <timestamp>");
+        eprintln!("  renew <prev_state_hex> <circle_utxo> <timestamp> <keep|rotate|reshuffle:<seed_hex>> <member_pubkey_hex...>");
         std::process::exit(1);
     }
 
@@ -21,8 +29,9 @@ fn main() {
 
     match command.as_str() {
         "add_member" => {
-            if args.len() != 6 {
-                eprintln!("Usage: update_state add_member <prev_state_hex> <new_member_pubkey_hex> <payout_round> <joined_at_timestamp>");
+            if args.len() != 6 && args.len() != 7 {
+                eprintln!("Usage: update_state add_member <prev_state_hex> <new_member_pubkey_hex> <payout_round> <joined_at_timestamp> [collateral_sats]");
+                eprintln!("  collateral_sats: bond the new member deposits; must match the circle's collateral_per_member");
                 std::process::exit(1);
             }
 
@@ -36,6 +45,18 @@ fn main() {
             let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
                 .expect("Failed to deserialize previous state");
 
+            // The bond deposited with the join must be the one the circle requires
+            if let Some(collateral) = args.get(6) {
+                let collateral: u64 = collateral.parse().expect("Invalid collateral_sats");
+                if collateral != state.collateral_per_member {
+                    eprintln!(
+                        "Error: circle requires {} sats of collateral, got {}",
+                        state.collateral_per_member, collateral
+                    );
+                    std::process::exit(1);
+                }
+            }
+
             // Parse new member pubkey
             if new_member_pubkey_hex.len() != 66 {
                 eprintln!("Error: new_member_pubkey must be 66 hex characters (33 bytes)");
@@ -45,10 +66,52 @@ fn main() {
                 hex::decode(new_member_pubkey_hex).expect("Invalid hex for new_member_pubkey");
             let new_member_pubkey = PubKey(new_member_pubkey_bytes);
 
-            // Add member
+            // Add member, reporting the hash the new member signs for the witness
+            let action = CircleAction::Join {
+                pubkey: new_member_pubkey,
+                payout_round,
+                joined_at,
+            };
+            eprintln!("Signing hash: {}", hex::encode(state.signing_hash(&action)));
+            state.apply(&action).expect("Failed to add member");
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "start_circle" => {
+            if args.len() != 5 {
+                eprintln!("Usage: update_state start_circle <prev_state_hex> <creator_pubkey_hex> <timestamp>");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let creator_pubkey_hex = &args[3];
+            let timestamp: u64 = args[4].parse().expect("Invalid timestamp");
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse creator pubkey
+            if creator_pubkey_hex.len() != 66 {
+                eprintln!("Error: creator_pubkey must be 66 hex characters (33 bytes)");
+                std::process::exit(1);
+            }
+            let creator_pubkey_bytes =
+                hex::decode(creator_pubkey_hex).expect("Invalid hex for creator_pubkey");
+            let creator_pubkey = PubKey(creator_pubkey_bytes);
+
+            // Start circle
             state
-                .add_member(new_member_pubkey, payout_round, joined_at)
-                .expect("Failed to add member");
+                .start_circle(&creator_pubkey, timestamp)
+                .expect("Failed to start circle");
 
             // Serialize updated state
             let mut serialized = Vec::new();
@@ -94,10 +157,107 @@ fn main() {
             let mut txid = [0u8; 32];
             txid.copy_from_slice(&txid_bytes);
 
-            // Record contribution
+            // Record contribution, reporting the hash the contributor signs for
+            // the witness
+            let action = CircleAction::Contribute {
+                pubkey: contributor_pubkey,
+                amount,
+                timestamp,
+                txid,
+            };
+            eprintln!("Signing hash: {}", hex::encode(state.signing_hash(&action)));
+            state.apply(&action).expect("Failed to record contribution");
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "prepay" => {
+            if args.len() != 7 {
+                eprintln!("Usage: update_state prepay <prev_state_hex> <member_pubkey_hex> <amount> <timestamp> <txid_hex>");
+                eprintln!("  amount: pays what the member still owes this round; the rest is held for later rounds");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let member_pubkey_hex = &args[3];
+            let amount: u64 = args[4].parse().expect("Invalid amount");
+            let timestamp: u64 = args[5].parse().expect("Invalid timestamp");
+            let txid_hex = &args[6];
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse member pubkey
+            if member_pubkey_hex.len() != 66 {
+                eprintln!("Error: member_pubkey must be 66 hex characters (33 bytes)");
+                std::process::exit(1);
+            }
+            let member_pubkey_bytes =
+                hex::decode(member_pubkey_hex).expect("Invalid hex for member_pubkey");
+            let member_pubkey = PubKey(member_pubkey_bytes);
+
+            // Parse txid
+            if txid_hex.len() != 64 {
+                eprintln!("Error: txid must be 64 hex characters (32 bytes)");
+                std::process::exit(1);
+            }
+            let txid_bytes = hex::decode(txid_hex).expect("Invalid hex for txid");
+            let mut txid = [0u8; 32];
+            txid.copy_from_slice(&txid_bytes);
+
+            // Record prepayment
+            state
+                .prepay(&member_pubkey, amount, timestamp, txid)
+                .expect("Failed to record prepayment");
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "mark_defaults" => {
+            if args.len() < 5 {
+                eprintln!("Usage: update_state mark_defaults <prev_state_hex> <timestamp> <approver_pubkey_hex...>");
+                eprintln!("  approvers: every member being marked, or a majority of the members drawn from those who paid");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let timestamp: u64 = args[3].parse().expect("Invalid timestamp");
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse approver pubkeys
+            let mut approvals = Vec::new();
+            for approver_hex in &args[4..] {
+                if approver_hex.len() != 66 {
+                    eprintln!("Error: approver pubkeys must be 66 hex characters (33 bytes)");
+                    std::process::exit(1);
+                }
+                approvals.push(PubKey(
+                    hex::decode(approver_hex).expect("Invalid hex for approver pubkey"),
+                ));
+            }
+
+            // Mark members who missed the round
             state
-                .record_contribution(&contributor_pubkey, amount, timestamp, txid)
-                .expect("Failed to record contribution");
+                .mark_defaults(&approvals, timestamp)
+                .expect("Failed to mark defaults");
 
             // Serialize updated state
             let mut serialized = Vec::new();
@@ -108,6 +268,245 @@ fn main() {
             println!("{}", serialized_hex);
         }
 
+        "remove_member" => {
+            if args.len() != 4 {
+                eprintln!("Usage: update_state remove_member <prev_state_hex> <member_pubkey_hex>");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let member_pubkey_hex = &args[3];
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse member pubkey
+            if member_pubkey_hex.len() != 66 {
+                eprintln!("Error: member_pubkey must be 66 hex characters (33 bytes)");
+                std::process::exit(1);
+            }
+            let member_pubkey_bytes =
+                hex::decode(member_pubkey_hex).expect("Invalid hex for member_pubkey");
+            let member_pubkey = PubKey(member_pubkey_bytes);
+
+            // Remove member
+            let refund = state
+                .remove_member(&member_pubkey)
+                .expect("Failed to remove member");
+            if refund > 0 {
+                eprintln!("Collateral to refund: {} sats", refund);
+            }
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "transfer_seat" => {
+            if args.len() != 5 {
+                eprintln!("Usage: update_state transfer_seat <prev_state_hex> <from_pubkey_hex> <to_pubkey_hex>");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let from_pubkey_hex = &args[3];
+            let to_pubkey_hex = &args[4];
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse both pubkeys
+            if from_pubkey_hex.len() != 66 || to_pubkey_hex.len() != 66 {
+                eprintln!("Error: pubkeys must be 66 hex characters (33 bytes)");
+                std::process::exit(1);
+            }
+            let from_pubkey =
+                PubKey(hex::decode(from_pubkey_hex).expect("Invalid hex for from_pubkey"));
+            let to_pubkey = PubKey(hex::decode(to_pubkey_hex).expect("Invalid hex for to_pubkey"));
+
+            // Transfer seat
+            state
+                .transfer_seat(&from_pubkey, to_pubkey)
+                .expect("Failed to transfer seat");
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "cancel" => {
+            if args.len() < 4 {
+                eprintln!("Usage: update_state cancel <prev_state_hex> <approver_pubkey_hex...>");
+                eprintln!("  approvers: members approving the cancellation, at least the circle's cancel quorum");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse approver pubkeys
+            let mut approvals = Vec::new();
+            for approver_hex in &args[3..] {
+                if approver_hex.len() != 66 {
+                    eprintln!("Error: approver pubkeys must be 66 hex characters (33 bytes)");
+                    std::process::exit(1);
+                }
+                approvals.push(PubKey(
+                    hex::decode(approver_hex).expect("Invalid hex for approver pubkey"),
+                ));
+            }
+
+            // Cancel the circle and report what each member gets back: pool
+            // refund, collateral and prepaid escrow of all their seats, paid by
+            // a single output
+            let held: Vec<u64> = state
+                .members
+                .iter()
+                .map(|m| m.collateral + m.prepaid)
+                .collect();
+            state.cancel(&approvals).expect("Failed to cancel circle");
+            let refunds = per_key(
+                state
+                    .members
+                    .iter()
+                    .zip(held)
+                    .map(|(m, held)| (&m.pubkey, m.refund + held)),
+            );
+            for (pubkey, amount) in refunds {
+                eprintln!(
+                    "Refund to {}: {} sats",
+                    hex::encode(pubkey.as_bytes()),
+                    amount
+                );
+            }
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "settle" => {
+            if args.len() != 4 {
+                eprintln!("Usage: update_state settle <prev_state_hex> <timestamp>");
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let timestamp: u64 = args[3].parse().expect("Invalid timestamp");
+
+            // Deserialize previous state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let mut state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Settle the circle and report the collateral it returns, one
+            // output per member
+            let collateral: Vec<u64> = state.members.iter().map(|m| m.collateral).collect();
+            state.settle(timestamp).expect("Failed to settle circle");
+            let returned = per_key(
+                state
+                    .members
+                    .iter()
+                    .zip(collateral)
+                    .map(|(m, collateral)| (&m.pubkey, collateral)),
+            );
+            for (pubkey, collateral) in returned {
+                eprintln!(
+                    "Collateral to return to {}: {} sats",
+                    hex::encode(pubkey.as_bytes()),
+                    collateral
+                );
+            }
+
+            // Serialize updated state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&state, &mut serialized)
+                .expect("Failed to serialize updated state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
+        "renew" => {
+            if args.len() < 7 {
+                eprintln!("Usage: update_state renew <prev_state_hex> <circle_utxo> <timestamp> <keep|rotate|reshuffle:<seed_hex>> <member_pubkey_hex...>");
+                eprintln!("  circle_utxo: txid:vout of the settled circle, spent by the renewal");
+                eprintln!(
+                    "  members: keys carried over to the new circle (each signs the renewal)"
+                );
+                std::process::exit(1);
+            }
+
+            let prev_state_hex = &args[2];
+            let circle_utxo = UtxoId::from_str(&args[3]).expect("Invalid circle_utxo");
+            let timestamp: u64 = args[4].parse().expect("Invalid timestamp");
+
+            // Deserialize settled state
+            let prev_state_bytes = hex::decode(prev_state_hex).expect("Invalid hex for prev_state");
+            let state: CircleState = ciborium::de::from_reader(&prev_state_bytes[..])
+                .expect("Failed to deserialize previous state");
+
+            // Parse payout order
+            let order = match args[5].split_once(':') {
+                None if args[5] == "keep" => RenewalOrder::Keep,
+                None if args[5] == "rotate" => RenewalOrder::Rotate,
+                Some(("reshuffle", seed_hex)) if seed_hex.len() == 64 => {
+                    let mut seed = [0u8; 32];
+                    seed.copy_from_slice(&hex::decode(seed_hex).expect("Invalid hex for seed"));
+                    RenewalOrder::Reshuffle { seed }
+                }
+                _ => {
+                    eprintln!("Error: order must be keep, rotate or reshuffle:<64 hex chars>");
+                    std::process::exit(1);
+                }
+            };
+
+            // Parse renewing members
+            let mut members = Vec::new();
+            for member_hex in &args[6..] {
+                if member_hex.len() != 66 {
+                    eprintln!("Error: member pubkeys must be 66 hex characters (33 bytes)");
+                    std::process::exit(1);
+                }
+                members.push(PubKey(
+                    hex::decode(member_hex).expect("Invalid hex for member pubkey"),
+                ));
+            }
+
+            // Create the next cycle
+            let renewed = state
+                .renew(&circle_utxo, timestamp, &members, order)
+                .expect("Failed to renew circle");
+
+            // Serialize new state
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(&renewed, &mut serialized)
+                .expect("Failed to serialize renewed state");
+
+            let serialized_hex = hex::encode(&serialized);
+            println!("{}", serialized_hex);
+        }
+
         _ => {
             eprintln!("Unknown command: {}", command);
             std::process::exit(1);
@@ -115,6 +514,19 @@ fn main() {
     }
 }
 
+/// Sum the non-zero amounts owed to each key, in order of first appearance
+#[cfg(not(target_arch = "wasm32"))]
+fn per_key<'a>(amounts: impl Iterator<Item = (&'a PubKey, u64)>) -> Vec<(&'a PubKey, u64)> {
+    let mut totals: Vec<(&PubKey, u64)> = Vec::new();
+    for (pubkey, amount) in amounts.filter(|(_, amount)| *amount > 0) {
+        match totals.iter_mut().find(|(key, _)| *key == pubkey) {
+            Some((_, total)) => *total += amount,
+            None => totals.push((pubkey, amount)),
+        }
+    }
+    totals
+}
+
 #[cfg(target_arch = "wasm32")]
 fn main() {
     eprintln!("This binary is not available for WASM targets");
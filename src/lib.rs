@@ -31,14 +31,16 @@ mod wasm;
 #[cfg(target_arch = "wasm32")]
 extern crate alloc;
 
-use charms_sdk::data::{App, Data, Transaction};
+use charms_sdk::data::{
+    charm_values, nft_state_preserved, App, Charms, Data, NativeOutput, Transaction, UtxoId, NFT,
+};
 use serde::{Deserialize, Serialize};
 
 // Import anyhow for error handling (BRO token pattern)
 #[cfg(target_arch = "wasm32")]
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 #[cfg(not(target_arch = "wasm32"))]
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 
 // Use alloc for WASM, std for native
 #[cfg(target_arch = "wasm32")]
@@ -69,15 +71,45 @@ impl PubKey {
     pub fn from_bytes(bytes: [u8; 33]) -> Self {
         PubKey(bytes.to_vec())
     }
+
+    /// P2WPKH scriptPubKey paying to this key: `OP_0 <hash160(pubkey)>`
+    pub fn p2wpkh_script(&self) -> Vec<u8> {
+        use ripemd::Ripemd160;
+        use sha2::{Digest, Sha256};
+
+        let hash = Ripemd160::digest(Sha256::digest(&self.0));
+
+        let mut script = Vec::with_capacity(22);
+        script.push(0x00); // OP_0 (witness version 0)
+        script.push(0x14); // push 20 bytes
+        script.extend_from_slice(&hash);
+        script
+    }
+
+    /// Verify a compact (64-byte `r || s`) ECDSA signature by this key over a 32-byte hash
+    pub fn verify(&self, hash: &[u8; 32], signature: &[u8]) -> bool {
+        use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+
+        let Ok(key) = VerifyingKey::from_sec1_bytes(&self.0) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(signature) else {
+            return false;
+        };
+
+        key.verify_prehash(hash, &signature).is_ok()
+    }
 }
 
 /// Amount in satoshis
 pub type Satoshis = u64;
 
-/// Member information and participation history
+/// A seat in the circle: one share of the pot, with its own payout round and
+/// participation history. A member holding several shares owns several seats
+/// under the same key.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Member {
-    /// Member's public key
+    /// Public key of the member holding this seat
     pub pubkey: PubKey,
 
     /// Contribution amount per round (in satoshis)
@@ -94,6 +126,57 @@ pub struct Member {
 
     /// Timestamp when member joined (Unix timestamp)
     pub joined_at: u64,
+
+    /// Part of the pot this member gave up when taking it: the auction discount,
+    /// and under `DefaultPolicy::SkipPayout` whatever exceeded what they paid in
+    #[serde(default)]
+    pub auction_discount: Satoshis,
+
+    /// Total share of what other recipients gave up from their pots paid to this
+    /// member
+    #[serde(default)]
+    pub auction_dividends: Satoshis,
+
+    /// Collateral this member still has locked in the circle: the circle's
+    /// `collateral_per_member`, less whatever defaults took from it
+    #[serde(default)]
+    pub collateral: Satoshis,
+
+    /// Rounds this member failed to contribute to
+    #[serde(default)]
+    pub defaults: Vec<DefaultRecord>,
+
+    /// Circle fee deducted from this seat's payout
+    #[serde(default)]
+    pub fee_paid: Satoshis,
+
+    /// Amount paid out to this seat when it took the pot (after fees and discounts)
+    #[serde(default)]
+    pub payout_amount: Satoshis,
+
+    /// Share of the pool refunded to this seat when the circle was cancelled
+    #[serde(default)]
+    pub refund: Satoshis,
+
+    /// Prepaid contributions held in escrow for future rounds; each round's
+    /// contribution is taken from it when the round opens
+    #[serde(default)]
+    pub prepaid: Satoshis,
+}
+
+impl Member {
+    /// Whether this member has defaulted in any round
+    pub fn has_defaulted(&self) -> bool {
+        !self.defaults.is_empty()
+    }
+}
+
+/// Open bid in an auction circle: the discount a member accepts to take this round's pot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Bid {
+    pub pubkey: PubKey,
+    pub discount: Satoshis,
+    pub timestamp: u64,
 }
 
 /// Record of a single contribution
@@ -103,27 +186,383 @@ pub struct ContributionRecord {
     pub amount: Satoshis,
     pub timestamp: u64,
     pub txid: [u8; 32], // Transaction ID that included this contribution
+
+    /// Made after the round deadline, during the grace period
+    #[serde(default)]
+    pub late: bool,
+
+    /// Taken from the seat's prepaid balance when the round opened (`txid` is zero;
+    /// the prepayment itself was recorded by its own transaction)
+    #[serde(default)]
+    pub prepaid: bool,
+}
+
+/// Record of a missed contribution, made once the round closed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DefaultRecord {
+    pub round: u32,
+
+    /// Part of the round's contribution that was not paid
+    pub shortfall: Satoshis,
+
+    /// Part of the shortfall paid into the pool from the member's collateral
+    pub covered: Satoshis,
+
+    pub timestamp: u64,
+}
+
+/// Lifecycle phase of a circle
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CirclePhase {
+    /// Accepting members; no contributions yet
+    #[default]
+    Forming,
+
+    /// Membership frozen; rounds of contributions and payouts are running
+    Active,
+
+    /// All rounds paid out; remaining collateral awaits settlement
+    Settling,
+
+    /// Fully settled with a final settlement record; no further transitions
+    Complete,
+
+    /// Stopped before completion; no further transitions
+    Cancelled,
+}
+
+impl CirclePhase {
+    /// Whether a circle in this phase may move to `next` (staying put is always allowed)
+    pub fn can_transition_to(self, next: CirclePhase) -> bool {
+        use CirclePhase::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Forming, Active)
+                    | (Forming, Cancelled)
+                    | (Active, Settling)
+                    | (Active, Cancelled)
+                    | (Settling, Complete)
+            )
+    }
+
+    /// Whether the circle is finished for good: its state can no longer change
+    /// (a complete circle can only be renewed as a new circle)
+    pub fn is_terminal(self) -> bool {
+        matches!(self, CirclePhase::Complete | CirclePhase::Cancelled)
+    }
+}
+
+/// A member's final position in a settled circle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetPosition {
+    pub pubkey: PubKey,
+
+    /// Contributions paid into the pools, including collateral taken for defaults
+    pub paid_in: Satoshis,
+
+    /// Payouts and auction dividends received
+    pub paid_out: Satoshis,
+}
+
+impl NetPosition {
+    /// What the member gained (positive) or lost (negative) over the circle
+    pub fn net(&self) -> i128 {
+        self.paid_out as i128 - self.paid_in as i128
+    }
+}
+
+/// How payout rounds are assigned when a completed circle is renewed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RenewalOrder {
+    /// Same order as the completed circle (seniority circles always use this)
+    Keep,
+
+    /// Everyone moves up one round and the first recipient goes last
+    Rotate,
+
+    /// Random order drawn from a seed committed in the public input (`x`)
+    Reshuffle { seed: [u8; 32] },
+}
+
+/// Link from a renewed circle to the completed circle it continues
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Predecessor {
+    pub circle_id: [u8; 32],
+
+    /// `state_hash()` of the predecessor's final (settled) state
+    pub final_state_hash: [u8; 32],
+}
+
+/// Final summary written when a completed circle is settled
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Settlement {
+    pub total_paid_in: Satoshis,
+    pub total_paid_out: Satoshis,
+
+    /// Fees paid to the fee recipient over the circle's life
+    pub fees_paid: Satoshis,
+
+    /// Collateral returned to members by the settlement
+    pub collateral_returned: Satoshis,
+
+    /// One entry per member (all of a key's seats together), in joining order
+    pub positions: Vec<NetPosition>,
+
+    pub settled_at: u64,
+}
+
+/// How the order in which members receive the pot is decided
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PayoutPolicy {
+    /// Members are paid in the `payout_round` they picked when joining
+    #[default]
+    Fixed,
+
+    /// Each round's recipient is drawn among unpaid members, seeded by data
+    /// committed in the payout transaction (e.g. a recent block hash)
+    Lottery,
+
+    /// Longest-standing members are paid first (by `joined_at`, then join order)
+    Seniority,
+
+    /// Unpaid members bid the discount they accept to take the pot each round;
+    /// the highest bid wins and the discount is shared among all other members
+    Auction,
+}
+
+/// What happens when members miss a round's contribution
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DefaultPolicy {
+    /// The round's recipient is paid whatever was collected
+    #[default]
+    ReducedPot,
+
+    /// Defaulters who have not been paid yet lose their turn: they are moved
+    /// behind every member in good standing in the payout order, and their pot
+    /// is capped at what they paid in; the rest goes to the other members
+    SkipPayout,
+
+    /// The shortfall is taken from the defaulter's collateral; whatever the
+    /// collateral cannot cover reduces the pot
+    Collateral,
+}
+
+/// How the circle fee is computed from the pot being paid out
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FeeRate {
+    /// Parts per 10,000 of the pot, rounded down
+    BasisPoints(u32),
+
+    /// Fixed amount per payout; never more than the pot
+    Flat(Satoshis),
+}
+
+/// Fee charged on every payout and paid to the circle's organizer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CircleFee {
+    pub rate: FeeRate,
+
+    /// Key whose P2WPKH script receives the fee
+    pub recipient: PubKey,
+}
+
+/// Operation performed by a spell on the circle state.
+/// Carried CBOR-encoded in the witness (`w`) passed to `app_contract`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CircleAction {
+    /// Create a new circle (no previous state is spent)
+    CreateCircle,
+
+    /// Freeze membership and start the first round (by the circle's creator)
+    StartCircle { pubkey: PubKey, timestamp: u64 },
+
+    /// Add a seat to a circle that has not started yet; joining again with the
+    /// same key takes another share
+    Join {
+        pubkey: PubKey,
+        payout_round: u32,
+        joined_at: u64,
+    },
+
+    /// Give up a seat in a circle that has not started yet (the member's most
+    /// recently taken one; its collateral is refunded)
+    RemoveMember { pubkey: PubKey, timestamp: u64 },
+
+    /// Hand an active member's seats, with their obligations and payout rights,
+    /// to a new key (signed by both)
+    TransferSeat {
+        from: PubKey,
+        to: PubKey,
+        timestamp: u64,
+    },
+
+    /// Record a member's contribution (in full or an installment) for the current
+    /// round, for the first of their seats that still owes
+    Contribute {
+        pubkey: PubKey,
+        amount: Satoshis,
+        timestamp: u64,
+        txid: [u8; 32],
+    },
+
+    /// Pay ahead: whatever the member's seat still owes this round is recorded as
+    /// a contribution, and the rest is escrowed for the following rounds
+    Prepay {
+        pubkey: PubKey,
+        amount: Satoshis,
+        timestamp: u64,
+        txid: [u8; 32],
+    },
+
+    /// Bid for the current round's pot in an auction circle
+    Bid {
+        pubkey: PubKey,
+        discount: Satoshis,
+        timestamp: u64,
+    },
+
+    /// Once the round has closed, mark members who did not contribute as defaulted.
+    /// The approvers (each signs) attest the time: every member being marked, or
+    /// a majority of the members drawn from those who paid.
+    MarkDefaults {
+        approvals: Vec<PubKey>,
+        timestamp: u64,
+    },
+
+    /// Stop the circle and refund the pool to members pro rata to what they are
+    /// owed. Needs the circle's cancel quorum of approvals (each approver signs).
+    Cancel {
+        approvals: Vec<PubKey>,
+        timestamp: u64,
+    },
+
+    /// Close a circle whose rounds are all paid out: write the settlement
+    /// record and return the remaining collateral (signed by the member who attests
+    /// the time)
+    Settle { pubkey: PubKey, timestamp: u64 },
+
+    /// Start a new cycle of a settled circle with the members listed (each
+    /// signs); spends the settled circle and creates the new one
+    Renew {
+        members: Vec<PubKey>,
+        order: RenewalOrder,
+        timestamp: u64,
+    },
+
+    /// Pay the pool out to the member scheduled for the current round. The
+    /// contract cannot read the chain's time, so a majority of the members
+    /// approve (each approver signs) and attests that the round is due.
+    Payout {
+        approvals: Vec<PubKey>,
+        timestamp: u64,
+        /// Lottery seed; must match the seed committed in the public input (`x`),
+        /// and the approvers sign it (e.g. a block hash they can all check)
+        #[serde(default)]
+        seed: Option<[u8; 32]>,
+    },
+}
+
+impl CircleAction {
+    /// Members whose signatures are required to authorize this action
+    pub fn signers(&self) -> Vec<&PubKey> {
+        match self {
+            CircleAction::StartCircle { pubkey, .. }
+            | CircleAction::Join { pubkey, .. }
+            | CircleAction::RemoveMember { pubkey, .. }
+            | CircleAction::Contribute { pubkey, .. }
+            | CircleAction::Prepay { pubkey, .. }
+            | CircleAction::Bid { pubkey, .. }
+            | CircleAction::Settle { pubkey, .. } => [pubkey].into(),
+            CircleAction::TransferSeat { from, to, .. } => [from, to].into(),
+            CircleAction::MarkDefaults { approvals, .. }
+            | CircleAction::Cancel { approvals, .. }
+            | CircleAction::Payout { approvals, .. } => approvals.iter().collect(),
+            CircleAction::Renew { members, .. } => members.iter().collect(),
+            CircleAction::CreateCircle => Vec::new(),
+        }
+    }
+
+    /// Time at which the action takes effect (`None` for circle creation)
+    pub fn timestamp(&self) -> Option<u64> {
+        match self {
+            CircleAction::CreateCircle => None,
+            CircleAction::StartCircle { timestamp, .. }
+            | CircleAction::Join {
+                joined_at: timestamp,
+                ..
+            }
+            | CircleAction::RemoveMember { timestamp, .. }
+            | CircleAction::TransferSeat { timestamp, .. }
+            | CircleAction::Contribute { timestamp, .. }
+            | CircleAction::Prepay { timestamp, .. }
+            | CircleAction::Bid { timestamp, .. }
+            | CircleAction::MarkDefaults { timestamp, .. }
+            | CircleAction::Cancel { timestamp, .. }
+            | CircleAction::Settle { timestamp, .. }
+            | CircleAction::Renew { timestamp, .. }
+            | CircleAction::Payout { timestamp, .. } => Some(*timestamp),
+        }
+    }
+}
+
+/// Public input (`x`) of the circle app: values every spell commits to publicly
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CircleInputs {
+    /// Time of the transaction; actions and new circles must carry exactly this time
+    pub timestamp: u64,
+
+    /// Lottery seed drawn for this payout, if any
+    #[serde(default)]
+    pub seed: Option<[u8; 32]>,
+}
+
+/// Witness passed to `app_contract`: the action performed by the spell and the
+/// signatures authorizing it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CircleWitness {
+    pub action: CircleAction,
+
+    /// Compact (64-byte `r || s`) ECDSA signatures over `CircleState::signing_hash`,
+    /// one per `CircleAction::signers()`, in the same order
+    #[serde(default)]
+    pub signatures: Vec<Vec<u8>>,
+}
+
+/// Seats needed to start a circle encoded before membership limits existed
+fn default_min_members() -> u32 {
+    2
+}
+
+/// Seat cap of a circle encoded before membership limits existed
+fn default_max_members() -> u32 {
+    12
 }
 
 /// The state of the ROSCA circle stored in Charms covenant
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct CircleState {
     /// Unique identifier for this ROSCA circle
     pub circle_id: [u8; 32],
 
-    /// List of all members in order of joining
+    /// All seats in order of joining; a key holding several shares appears once per seat
     pub members: Vec<Member>,
 
     /// Current round number (starts at 0)
     pub current_round: u32,
 
-    /// Total number of rounds (equals number of members)
+    /// Total number of rounds (equals number of seats)
     pub total_rounds: u32,
 
     /// Required contribution amount per member per round
     pub contribution_per_round: Satoshis,
 
-    /// Index of member who receives payout this round
+    /// Refundable bond each member locks in the circle when joining
+    #[serde(default)]
+    pub collateral_per_member: Satoshis,
+
+    /// Index of the seat that receives payout this round
     pub current_payout_index: usize,
 
     /// Total pool collected in current round
@@ -138,11 +577,59 @@ pub struct CircleState {
     /// Duration of each round in seconds (e.g., 30 days = 2592000)
     pub round_duration: u64,
 
-    /// Whether the circle has completed all rounds
+    /// Seconds after the round deadline during which late contributions are accepted
+    #[serde(default)]
+    pub grace_period: u64,
+
+    /// Seats that must be taken before the circle can start
+    #[serde(default = "default_min_members")]
+    pub min_members: u32,
+
+    /// Most seats the circle accepts
+    #[serde(default = "default_max_members")]
+    pub max_members: u32,
+
+    /// Whether the circle has completed all rounds (phase is Settling or Complete)
     pub is_complete: bool,
 
+    /// Lifecycle phase
+    #[serde(default)]
+    pub phase: CirclePhase,
+
     /// Hash of the previous state for chain verification
     pub prev_state_hash: [u8; 32],
+
+    /// How the payout order is decided (chosen at creation)
+    #[serde(default)]
+    pub payout_policy: PayoutPolicy,
+
+    /// What happens when members miss a contribution (chosen at creation)
+    #[serde(default)]
+    pub default_policy: DefaultPolicy,
+
+    /// Auction circles: open bids for the current round, in the order placed
+    #[serde(default)]
+    pub bids: Vec<Bid>,
+
+    /// Fee deducted from each payout, if any (chosen at creation)
+    #[serde(default)]
+    pub fee: Option<CircleFee>,
+
+    /// Total fees paid to the fee recipient so far
+    #[serde(default)]
+    pub fees_collected: Satoshis,
+
+    /// Distinct members whose approval cancels the circle (0 means all of them)
+    #[serde(default)]
+    pub cancel_quorum: u32,
+
+    /// Final summary, written when the circle is settled (phase Complete)
+    #[serde(default)]
+    pub settlement: Option<Settlement>,
+
+    /// Completed circle this one renews, if any
+    #[serde(default)]
+    pub predecessor: Option<Predecessor>,
 }
 
 impl CircleState {
@@ -152,6 +639,8 @@ impl CircleState {
         contribution_per_round: Satoshis,
         round_duration: u64,
         created_at: u64,
+        min_members: u32,
+        max_members: u32,
     ) -> Self {
         Self {
             circle_id,
@@ -159,441 +648,4931 @@ impl CircleState {
             current_round: 0,
             total_rounds: 0,
             contribution_per_round,
+            collateral_per_member: 0,
             current_payout_index: 0,
             current_pool: 0,
             created_at,
             round_started_at: created_at,
             round_duration,
+            grace_period: 0,
+            min_members,
+            max_members,
             is_complete: false,
+            phase: CirclePhase::Forming,
             prev_state_hash: [0u8; 32],
+            payout_policy: PayoutPolicy::Fixed,
+            default_policy: DefaultPolicy::ReducedPot,
+            bids: Vec::new(),
+            fee: None,
+            fees_collected: 0,
+            cancel_quorum: 0,
+            settlement: None,
+            predecessor: None,
         }
     }
 
-    /// Calculate state hash for covenant verification
-    /// Uses the same serialization as charms_data for consistency
-    pub fn state_hash(&self) -> [u8; 32] {
+    /// Derive a circle's id from the first outpoint spent by its creation transaction.
+    /// Outpoints can only be spent once, so ids cannot collide.
+    pub fn derive_circle_id(funding_outpoint: &UtxoId) -> [u8; 32] {
         use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-
-        // Hash the serialized state using CBOR to match what charms_data uses internally
-        // Use ciborium which works in no_std (same as Charms SDK uses)
-        let mut bytes = Vec::new();
-        match ciborium::ser::into_writer(self, &mut bytes) {
-            Ok(_) => {}
-            Err(_) => return [0u8; 32],
-        }
 
-        hasher.update(&bytes);
+        let mut circle_id = [0u8; 32];
+        circle_id.copy_from_slice(&Sha256::digest(funding_outpoint.to_bytes()));
+        circle_id
+    }
 
-        let result = hasher.finalize();
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&result);
-        hash
+    /// Create a new circle funded by `funding_outpoint`, with its creator as the only
+    /// member (payout_round 0). This is the only state `app_contract` accepts at genesis.
+    pub fn create(
+        funding_outpoint: &UtxoId,
+        contribution_per_round: Satoshis,
+        round_duration: u64,
+        created_at: u64,
+        min_members: u32,
+        max_members: u32,
+        creator: PubKey,
+    ) -> Result<Self, String> {
+        let mut state = Self::new(
+            Self::derive_circle_id(funding_outpoint),
+            contribution_per_round,
+            round_duration,
+            created_at,
+            min_members,
+            max_members,
+        );
+        state.validate_member_limits()?;
+        state.add_member(creator, 0, created_at)?;
+        Ok(state)
     }
 
-    /// Add a new member to the circle (only allowed before first round starts)
-    pub fn add_member(
-        &mut self,
-        pubkey: PubKey,
-        payout_round: u32,
-        timestamp: u64,
-    ) -> Result<(), String> {
-        // Validation
-        if self.current_round > 0 {
-            return Err("Cannot add members after circle has started".to_string());
+    /// Validate a freshly created circle: id derived from the funding outpoint,
+    /// the creator as the only member, round 0, an empty pool and no renewal or
+    /// settlement record
+    pub fn validate_genesis(&self, funding_outpoint: &UtxoId) -> Result<(), String> {
+        if self.circle_id != Self::derive_circle_id(funding_outpoint) {
+            return Err("Circle ID must be derived from the funding outpoint".to_string());
         }
 
-        if self.members.iter().any(|m| m.pubkey == pubkey) {
-            return Err("Member already exists".to_string());
+        if self.members.len() != 1 {
+            return Err(format!(
+                "New circle must have exactly one member, got {}",
+                self.members.len()
+            ));
         }
 
-        if payout_round as usize >= self.members.len() + 1 {
-            return Err("Invalid payout round".to_string());
+        if self.phase != CirclePhase::Forming {
+            return Err("New circle must be forming".to_string());
         }
 
-        let member = Member {
-            pubkey,
-            contribution_amount: self.contribution_per_round,
-            contribution_history: Vec::new(),
-            has_received_payout: false,
-            payout_round,
-            joined_at: timestamp,
-        };
+        if self.current_round != 0 || self.current_payout_index != 0 || self.is_complete {
+            return Err("New circle must start at round 0".to_string());
+        }
 
-        self.members.push(member);
-        self.total_rounds = self.members.len() as u32;
+        if self.current_pool != 0 || !self.members[0].contribution_history.is_empty() {
+            return Err("New circle must have an empty pool".to_string());
+        }
+
+        // Only a renewal links a circle to its predecessor
+        if self.predecessor.is_some() || self.settlement.is_some() {
+            return Err("New circle cannot carry a renewal or settlement record".to_string());
+        }
 
         Ok(())
     }
 
-    /// Record a member's contribution for the current round
-    pub fn record_contribution(
-        &mut self,
-        pubkey: &PubKey,
-        amount: Satoshis,
-        timestamp: u64,
-        txid: [u8; 32],
-    ) -> Result<(), String> {
-        if self.is_complete {
-            return Err("Circle is already complete".to_string());
+    /// A circle needs a contribution, at least two members to start and a cap it
+    /// can reach
+    pub fn validate_member_limits(&self) -> Result<(), String> {
+        if self.contribution_per_round == 0 {
+            return Err("Contribution per round must be positive".to_string());
         }
 
-        // Find the member
-        let member = self
-            .members
-            .iter_mut()
-            .find(|m| &m.pubkey == pubkey)
-            .ok_or("Member not found".to_string())?;
-
-        // Check if already contributed this round
-        if member
-            .contribution_history
-            .iter()
-            .any(|c| c.round == self.current_round)
-        {
-            return Err("Member already contributed this round".to_string());
+        if self.min_members < 2 {
+            return Err("Circle must require at least 2 members to start".to_string());
         }
 
-        // Validate contribution amount
-        if amount != self.contribution_per_round {
+        if self.max_members < self.min_members {
             return Err(format!(
-                "Invalid contribution amount. Expected: {}, Got: {}",
-                self.contribution_per_round, amount
+                "max_members ({}) is below min_members ({})",
+                self.max_members, self.min_members
             ));
         }
 
-        // Record the contribution
-        member.contribution_history.push(ContributionRecord {
-            round: self.current_round,
-            amount,
-            timestamp,
-            txid,
-        });
-
-        self.current_pool += amount;
-
         Ok(())
     }
 
-    /// Check if all members have contributed for the current round
-    pub fn is_round_fully_funded(&self) -> bool {
-        let contributions_this_round = self
-            .members
-            .iter()
-            .filter(|m| {
-                m.contribution_history
-                    .iter()
-                    .any(|c| c.round == self.current_round)
-            })
-            .count();
-
-        contributions_this_round == self.members.len()
-    }
+    /// A fee takes at most the whole pot and goes to a valid key
+    pub fn validate_fee(&self) -> Result<(), String> {
+        let Some(fee) = &self.fee else {
+            return Ok(());
+        };
 
-    /// Execute payout to the designated member for current round
-    pub fn execute_payout(&mut self, timestamp: u64) -> Result<(PubKey, Satoshis), String> {
-        if self.is_complete {
-            return Err("Circle is already complete".to_string());
+        if let FeeRate::BasisPoints(bps) = fee.rate {
+            if bps > 10_000 {
+                return Err(format!("Fee of {} basis points exceeds 100%", bps));
+            }
         }
 
-        if !self.is_round_fully_funded() {
-            return Err("Round is not fully funded yet".to_string());
+        if fee.recipient.as_bytes().len() != 33 {
+            return Err("Fee recipient must be a 33-byte compressed public key".to_string());
         }
 
-        if self.current_payout_index >= self.members.len() {
-            return Err("Invalid payout index".to_string());
+        Ok(())
+    }
+
+    /// Fee charged on a payout of `pot` sats. Basis points are rounded down, so
+    /// the remainder of the division stays with the recipient.
+    pub fn fee_for(&self, pot: Satoshis) -> Result<Satoshis, String> {
+        match self.fee.as_ref().map(|fee| fee.rate) {
+            None => Ok(0),
+            Some(FeeRate::BasisPoints(bps)) => pot
+                .checked_mul(bps as Satoshis)
+                .map(|parts| parts / 10_000)
+                .filter(|fee| *fee <= pot)
+                .ok_or_else(|| format!("Fee overflows on a pot of {} sats", pot)),
+            Some(FeeRate::Flat(amount)) => Ok(amount.min(pot)),
         }
+    }
 
-        let member = &mut self.members[self.current_payout_index];
+    /// Calculate state hash for covenant verification
+    /// Uses the same serialization as charms_data for consistency
+    pub fn state_hash(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
 
-        if member.has_received_payout {
-            return Err("Member has already received payout".to_string());
+        // Hash the serialized state using CBOR to match what charms_data uses internally
+        // Use ciborium which works in no_std (same as Charms SDK uses)
+        let mut bytes = Vec::new();
+        match ciborium::ser::into_writer(self, &mut bytes) {
+            Ok(_) => {}
+            Err(_) => return [0u8; 32],
         }
 
-        let payout_amount = self.current_pool;
-        let recipient = member.pubkey.clone();
-
-        member.has_received_payout = true;
+        hasher.update(&bytes);
+
+        let result = hasher.finalize();
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&result);
+        hash
+    }
+
+    /// Hash a member signs to authorize `action` on this state:
+    /// sha256("charmcircle/action" || circle_id || state_hash() || CBOR(action)).
+    /// Committing to the state hash makes signatures single-use.
+    pub fn signing_hash(&self, action: &CircleAction) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        let mut action_bytes = Vec::new();
+        if ciborium::ser::into_writer(action, &mut action_bytes).is_err() {
+            return [0u8; 32];
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"charmcircle/action");
+        hasher.update(self.circle_id);
+        hasher.update(self.state_hash());
+        hasher.update(&action_bytes);
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&hasher.finalize());
+        hash
+    }
+
+    /// Check that every member required by `action` signed it against this state
+    pub fn verify_authorization(
+        &self,
+        action: &CircleAction,
+        signatures: &[Vec<u8>],
+    ) -> Result<(), String> {
+        let signers = action.signers();
+        if signatures.len() != signers.len() {
+            return Err(format!(
+                "Expected {} signature(s), got {}",
+                signers.len(),
+                signatures.len()
+            ));
+        }
+
+        let hash = self.signing_hash(action);
+        for (signer, signature) in signers.into_iter().zip(signatures) {
+            if !signer.verify(&hash, signature) {
+                return Err("Invalid member signature".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fail unless `pubkey` holds a seat, so outsiders cannot stamp the time of
+    /// member-signed actions
+    fn require_member(&self, pubkey: &PubKey) -> Result<(), String> {
+        if !self.members.iter().any(|m| &m.pubkey == pubkey) {
+            return Err("Member not found".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fail unless the circle is in `phase`
+    fn require_phase(&self, phase: CirclePhase, operation: &str) -> Result<(), String> {
+        if self.phase != phase {
+            return Err(format!(
+                "Cannot {} while circle is {:?}",
+                operation, self.phase
+            ));
+        }
+        Ok(())
+    }
+
+    /// Freeze membership and start the first round. Only the creator can start it.
+    pub fn start_circle(&mut self, pubkey: &PubKey, timestamp: u64) -> Result<(), String> {
+        self.require_phase(CirclePhase::Forming, "start circle")?;
+
+        if self.members.first().map(|m| &m.pubkey) != Some(pubkey) {
+            return Err("Only the circle creator can start it".to_string());
+        }
+
+        if self.members.len() < self.min_members as usize {
+            return Err(format!(
+                "Circle needs {} members to start, has {}",
+                self.min_members,
+                self.members.len()
+            ));
+        }
+
+        self.prev_state_hash = self.state_hash();
+        self.phase = CirclePhase::Active;
+        self.round_started_at = timestamp;
+
+        // Close the gaps between the slots members picked, keeping their order,
+        // so the schedule runs over rounds 0..n
+        let mut schedule: Vec<usize> = (0..self.members.len()).collect();
+        schedule.sort_by_key(|&i| self.members[i].payout_round);
+        for (round, index) in schedule.into_iter().enumerate() {
+            self.members[index].payout_round = round as u32;
+        }
+        self.current_payout_index = self.payout_index_for_round(0).unwrap_or(0);
+
+        Ok(())
+    }
+
+    /// Add a seat for `pubkey` to the circle (only allowed while forming). A key
+    /// that already holds a seat takes another share.
+    /// `payout_round` is honored for `PayoutPolicy::Fixed`, and must be a free slot
+    /// below `max_members`; other policies assign the slot themselves (by
+    /// seniority, or last until drawn in a lottery). Gaps between slots are
+    /// closed when the circle starts.
+    pub fn add_member(
+        &mut self,
+        pubkey: PubKey,
+        payout_round: u32,
+        timestamp: u64,
+    ) -> Result<(), String> {
+        // Validation
+        self.require_phase(CirclePhase::Forming, "add members")?;
+
+        if self.members.len() >= self.max_members as usize {
+            return Err(format!("Circle is full ({} seats)", self.max_members));
+        }
+
+        // Joining times order seniority, so they cannot be backdated
+        let earliest = self
+            .members
+            .last()
+            .map_or(self.created_at, |m| m.joined_at.max(self.created_at));
+        if timestamp < earliest {
+            return Err(format!("Cannot join before {}", earliest));
+        }
+
+        let payout_round = match self.payout_policy {
+            PayoutPolicy::Fixed => payout_round,
+            PayoutPolicy::Lottery | PayoutPolicy::Auction | PayoutPolicy::Seniority => {
+                self.members.len() as u32
+            }
+        };
+
+        if payout_round >= self.max_members {
+            return Err("Invalid payout round".to_string());
+        }
+
+        // Each slot in the payout schedule is taken by one seat; nobody else's
+        // turn moves without their signature
+        if self.members.iter().any(|m| m.payout_round == payout_round) {
+            return Err(format!("Payout round {} is already taken", payout_round));
+        }
+
+        // Link to the state being replaced
+        self.prev_state_hash = self.state_hash();
+
+        let member = Member {
+            pubkey,
+            contribution_amount: self.contribution_per_round,
+            contribution_history: Vec::new(),
+            has_received_payout: false,
+            payout_round,
+            joined_at: timestamp,
+            auction_discount: 0,
+            auction_dividends: 0,
+            collateral: self.collateral_per_member,
+            defaults: Vec::new(),
+            fee_paid: 0,
+            payout_amount: 0,
+            refund: 0,
+            prepaid: 0,
+        };
+
+        self.members.push(member);
+        self.total_rounds = self.members.len() as u32;
+        self.current_payout_index = self.payout_index_for_round(self.current_round).unwrap_or(0);
+
+        Ok(())
+    }
+
+    /// Remove a member's most recently taken seat from a circle that has not
+    /// started yet. Seats scheduled after it move up one round. Returns the
+    /// collateral to refund.
+    pub fn remove_member(&mut self, pubkey: &PubKey) -> Result<Satoshis, String> {
+        self.require_phase(CirclePhase::Forming, "remove members")?;
+
+        let index = self
+            .members
+            .iter()
+            .rposition(|m| &m.pubkey == pubkey)
+            .ok_or("Member not found".to_string())?;
+
+        if index == 0 {
+            return Err("The circle creator cannot give up their first seat".to_string());
+        }
+
+        self.prev_state_hash = self.state_hash();
+
+        let removed = self.members.remove(index);
+        for member in self.members.iter_mut() {
+            if member.payout_round > removed.payout_round {
+                member.payout_round -= 1;
+            }
+        }
+        self.total_rounds = self.members.len() as u32;
+        self.current_payout_index = self.payout_index_for_round(self.current_round).unwrap_or(0);
+
+        Ok(removed.collateral)
+    }
+
+    /// Hand every seat a member holds in an active circle to a new key. Seats keep
+    /// their contribution history, payout round, collateral and any open bid.
+    pub fn transfer_seat(&mut self, from: &PubKey, to: PubKey) -> Result<(), String> {
+        self.require_phase(CirclePhase::Active, "transfer seats")?;
+
+        if self.members.iter().any(|m| m.pubkey == to) {
+            return Err("New key is already a member".to_string());
+        }
+
+        if !self.members.iter().any(|m| &m.pubkey == from) {
+            return Err("Member not found".to_string());
+        }
+
+        self.prev_state_hash = self.state_hash();
+
+        for bid in self.bids.iter_mut().filter(|bid| &bid.pubkey == from) {
+            bid.pubkey = to.clone();
+        }
+        for seat in self.members.iter_mut().filter(|m| &m.pubkey == from) {
+            seat.pubkey = to.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Total `seat` has contributed to `round` so far (installments add up)
+    fn paid_for_round(seat: &Member, round: u32) -> Satoshis {
+        seat.contribution_history
+            .iter()
+            .filter(|c| c.round == round)
+            .map(|c| c.amount)
+            .sum()
+    }
+
+    /// Whether `seat` has paid the current round in full, or defaulted on it
+    fn has_settled_round(&self, seat: &Member) -> bool {
+        Self::paid_for_round(seat, self.current_round) >= self.contribution_per_round
+            || seat.defaults.iter().any(|d| d.round == self.current_round)
+    }
+
+    /// Record a member's contribution for the current round, for the first of
+    /// their seats (in joining order) that still owes and has not defaulted.
+    /// Installments are accepted until the seat has paid `contribution_per_round`;
+    /// paying more than the seat still owes is rejected.
+    pub fn record_contribution(
+        &mut self,
+        pubkey: &PubKey,
+        amount: Satoshis,
+        timestamp: u64,
+        txid: [u8; 32],
+    ) -> Result<(), String> {
+        self.require_phase(CirclePhase::Active, "record contributions")?;
+        let late = self.check_contribution_time(timestamp)?;
+
+        let prev_state_hash = self.state_hash();
+
+        // Find the member's first seat still owing this round
+        let mut seats = self
+            .members
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| &m.pubkey == pubkey)
+            .peekable();
+        if seats.peek().is_none() {
+            return Err("Member not found".to_string());
+        }
+        let mut defaulted = false;
+        let index = seats
+            .find(|(_, seat)| {
+                defaulted |= seat.defaults.iter().any(|d| d.round == self.current_round);
+                !self.has_settled_round(seat)
+            })
+            .map(|(index, _)| index)
+            .ok_or_else(|| {
+                if defaulted {
+                    "Member already defaulted this round".to_string()
+                } else {
+                    "Member already contributed this round".to_string()
+                }
+            })?;
+
+        // Validate contribution amount against what the seat still owes
+        let owed = self.contribution_per_round
+            - Self::paid_for_round(&self.members[index], self.current_round);
+        if amount == 0 || amount > owed {
+            return Err(format!(
+                "Invalid contribution amount. Owed: {}, Got: {}",
+                owed, amount
+            ));
+        }
+
+        self.check_deposit(amount)?;
+        let current_pool = self
+            .current_pool
+            .checked_add(amount)
+            .ok_or("Pool overflow")?;
+
+        // Record the contribution
+        self.members[index]
+            .contribution_history
+            .push(ContributionRecord {
+                round: self.current_round,
+                amount,
+                timestamp,
+                txid,
+                late,
+                prepaid: false,
+            });
+
+        self.current_pool = current_pool;
+        self.prev_state_hash = prev_state_hash;
+
+        Ok(())
+    }
+
+    /// Fail unless the current round accepts contributions at `timestamp`;
+    /// returns whether they would be late
+    fn check_contribution_time(&self, timestamp: u64) -> Result<bool, String> {
+        if timestamp < self.round_started_at {
+            return Err("Contribution predates the current round".to_string());
+        }
+
+        if timestamp > self.contribution_cutoff() {
+            return Err(format!(
+                "Round {} closed for contributions at {}",
+                self.current_round,
+                self.contribution_cutoff()
+            ));
+        }
+
+        Ok(timestamp > self.round_deadline())
+    }
+
+    /// Rounds still to come after the current one
+    fn rounds_ahead(&self) -> u32 {
+        self.total_rounds.saturating_sub(self.current_round + 1)
+    }
+
+    /// Most a seat may hold in prepaid escrow: every round still to come
+    fn prepaid_limit(&self) -> Result<Satoshis, String> {
+        self.contribution_per_round
+            .checked_mul(self.rounds_ahead() as Satoshis)
+            .ok_or_else(|| "Prepaid limit overflow".to_string())
+    }
+
+    /// Pot of a round every seat paid in full
+    fn full_pot(&self) -> Result<Satoshis, String> {
+        self.contribution_per_round
+            .checked_mul(self.members.len() as Satoshis)
+            .ok_or_else(|| "Pot size overflow".to_string())
+    }
+
+    /// Pay ahead for a member: the first of their seats (in joining order) that can
+    /// take the whole `amount` records what it still owes this round as a
+    /// contribution and escrows the rest, which pays the following rounds as they
+    /// open. A seat cannot prepay beyond the end of the circle.
+    pub fn prepay(
+        &mut self,
+        pubkey: &PubKey,
+        amount: Satoshis,
+        timestamp: u64,
+        txid: [u8; 32],
+    ) -> Result<(), String> {
+        self.require_phase(CirclePhase::Active, "prepay")?;
+        let late = self.check_contribution_time(timestamp)?;
+
+        if !self.members.iter().any(|m| &m.pubkey == pubkey) {
+            return Err("Member not found".to_string());
+        }
+
+        if amount == 0 {
+            return Err("Prepayment must be positive".to_string());
+        }
+
+        let owed_now = |seat: &Member| {
+            if self.has_settled_round(seat) {
+                0
+            } else {
+                self.contribution_per_round - Self::paid_for_round(seat, self.current_round)
+            }
+        };
+        let limit = self.prepaid_limit()?;
+        let index = self
+            .members
+            .iter()
+            .position(|seat| {
+                &seat.pubkey == pubkey
+                    && amount <= owed_now(seat).saturating_add(limit.saturating_sub(seat.prepaid))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Prepayment of {} sats exceeds what the member owes for the rest of the circle",
+                    amount
+                )
+            })?;
+
+        self.check_deposit(amount)?;
+        let prev_state_hash = self.state_hash();
+
+        let now = amount.min(owed_now(&self.members[index]));
+        let current_pool = self.current_pool.checked_add(now).ok_or("Pool overflow")?;
+        let prepaid = self.members[index]
+            .prepaid
+            .checked_add(amount - now)
+            .ok_or("Prepaid balance overflow")?;
+        let seat = &mut self.members[index];
+        if now > 0 {
+            seat.contribution_history.push(ContributionRecord {
+                round: self.current_round,
+                amount: now,
+                timestamp,
+                txid,
+                late,
+                prepaid: false,
+            });
+        }
+        seat.prepaid = prepaid;
+
+        self.current_pool = current_pool;
+        self.prev_state_hash = prev_state_hash;
+
+        Ok(())
+    }
+
+    /// Pay the current round's contributions out of prepaid escrow, as it opens
+    fn apply_prepaid_credits(&mut self) {
+        let round = self.current_round;
+        let timestamp = self.round_started_at;
+        let contribution = self.contribution_per_round;
+
+        for seat in &mut self.members {
+            let credit = seat.prepaid.min(contribution);
+            if credit == 0 {
+                continue;
+            }
+            seat.prepaid -= credit;
+            seat.contribution_history.push(ContributionRecord {
+                round,
+                amount: credit,
+                timestamp,
+                txid: [0u8; 32],
+                late: false,
+                prepaid: true,
+            });
+            self.current_pool += credit;
+        }
+    }
+
+    /// Time at which the current round is due and its payout may run
+    pub fn round_deadline(&self) -> u64 {
+        self.round_started_at.saturating_add(self.round_duration)
+    }
+
+    /// Last time at which (late) contributions to the current round are accepted
+    pub fn contribution_cutoff(&self) -> u64 {
+        self.round_deadline().saturating_add(self.grace_period)
+    }
+
+    /// Check if every seat has paid the current round in full
+    pub fn is_round_fully_funded(&self) -> bool {
+        self.members
+            .iter()
+            .all(|m| Self::paid_for_round(m, self.current_round) == self.contribution_per_round)
+    }
+
+    /// Check if every seat has either contributed or been marked defaulted
+    /// for the current round
+    pub fn is_round_settled(&self) -> bool {
+        self.members.iter().all(|m| self.has_settled_round(m))
+    }
+
+    /// Whether `seat` has lost its turn under `DefaultPolicy::SkipPayout`
+    fn is_skipped(&self, seat: &Member) -> bool {
+        self.default_policy == DefaultPolicy::SkipPayout
+            && seat.has_defaulted()
+            && !seat.has_received_payout
+    }
+
+    /// The seat an auction bid by `pubkey` would win: the key's earliest scheduled
+    /// seat that is still unpaid and has not lost its turn
+    fn bidding_seat(&self, pubkey: &PubKey) -> Option<usize> {
+        (0..self.members.len())
+            .filter(|&i| {
+                let seat = &self.members[i];
+                &seat.pubkey == pubkey && !seat.has_received_payout && !self.is_skipped(seat)
+            })
+            .min_by_key(|&i| self.members[i].payout_round)
+    }
+
+    /// Once the round has closed, mark every seat that did not pay in full as
+    /// defaulted on the rest and apply the default policy. `approvals` must be
+    /// distinct members: every member being marked, or a payout quorum of members
+    /// who paid, so no one can stamp a time past the cutoff on their own.
+    pub fn mark_defaults(&mut self, approvals: &[PubKey], timestamp: u64) -> Result<(), String> {
+        self.require_phase(CirclePhase::Active, "mark defaults")?;
+        self.check_approvals(approvals, 1, "Marking defaults")?;
+
+        if timestamp <= self.contribution_cutoff() {
+            return Err(format!(
+                "Round {} is open for contributions until {}",
+                self.current_round,
+                self.contribution_cutoff()
+            ));
+        }
+
+        if self.is_round_settled() {
+            return Err("No outstanding contributions this round".to_string());
+        }
+
+        let round = self.current_round;
+        let marked: Vec<&PubKey> = self
+            .member_keys()
+            .into_iter()
+            .filter(|&key| {
+                self.members.iter().any(|m| {
+                    &m.pubkey == key && Self::paid_for_round(m, round) < self.contribution_per_round
+                })
+            })
+            .collect();
+        let paid_approvals = approvals.iter().filter(|a| !marked.contains(a)).count();
+        if !marked.iter().all(|&key| approvals.contains(key))
+            && paid_approvals < self.payout_approvals_needed()
+        {
+            return Err(format!(
+                "Marking defaults needs every marked member's approval or {} approvals from members who paid, got {}",
+                self.payout_approvals_needed(),
+                paid_approvals
+            ));
+        }
+
+        let prev_state_hash = self.state_hash();
+        let take_collateral = self.default_policy == DefaultPolicy::Collateral;
+
+        for member in &mut self.members {
+            let shortfall = self.contribution_per_round - Self::paid_for_round(member, round);
+            if shortfall == 0 {
+                continue;
+            }
+
+            let covered = if take_collateral {
+                shortfall.min(member.collateral)
+            } else {
+                0
+            };
+            member.collateral -= covered;
+            self.current_pool += covered;
+            member.defaults.push(DefaultRecord {
+                round,
+                shortfall,
+                covered,
+                timestamp,
+            });
+        }
+
+        // Unpaid defaulters go to the back of the payout order
+        if self.default_policy == DefaultPolicy::SkipPayout {
+            let mut unpaid: Vec<usize> = (0..self.members.len())
+                .filter(|&i| !self.members[i].has_received_payout)
+                .collect();
+            unpaid.sort_by_key(|&i| {
+                (
+                    self.members[i].has_defaulted(),
+                    self.members[i].payout_round,
+                )
+            });
+            for (offset, index) in unpaid.into_iter().enumerate() {
+                self.members[index].payout_round = round + offset as u32;
+            }
+            self.current_payout_index = self.payout_index_for_round(round).unwrap_or(0);
+
+            let skipped: Vec<PubKey> = self
+                .bids
+                .iter()
+                .filter(|bid| self.bidding_seat(&bid.pubkey).is_none())
+                .map(|bid| bid.pubkey.clone())
+                .collect();
+            self.bids.retain(|bid| !skipped.contains(&bid.pubkey));
+        }
+
+        self.prev_state_hash = prev_state_hash;
+
+        Ok(())
+    }
+
+    /// Index of the seat whose `payout_round` is `round`
+    pub fn payout_index_for_round(&self, round: u32) -> Option<usize> {
+        self.members.iter().position(|m| m.payout_round == round)
+    }
+
+    /// Place (or replace) a member's bid for the current round's pot in an auction circle
+    pub fn place_bid(
+        &mut self,
+        pubkey: &PubKey,
+        discount: Satoshis,
+        timestamp: u64,
+    ) -> Result<(), String> {
+        if self.payout_policy != PayoutPolicy::Auction {
+            return Err("Circle does not run auctions".to_string());
+        }
+
+        self.require_phase(CirclePhase::Active, "place bids")?;
+
+        if !self.members.iter().any(|m| &m.pubkey == pubkey) {
+            return Err("Member not found".to_string());
+        }
+
+        if self.bidding_seat(pubkey).is_none() {
+            return Err(if self
+                .members
+                .iter()
+                .any(|m| &m.pubkey == pubkey && self.is_skipped(m))
+            {
+                "Member lost their turn by defaulting"
+            } else {
+                "Member has already received payout"
+            }
+            .to_string());
+        }
+
+        let full_pot = self.full_pot()?;
+        if discount == 0 || discount >= full_pot {
+            return Err(format!(
+                "Bid discount must be between 1 and {} sats",
+                full_pot.saturating_sub(1)
+            ));
+        }
 
-        // Update state hash before transitioning
         self.prev_state_hash = self.state_hash();
 
-        // Reset pool and prepare for next round
-        self.current_pool = 0;
-        self.current_round += 1;
-        self.current_payout_index = (self.current_payout_index + 1) % self.members.len();
-        self.round_started_at = timestamp;
+        // A new bid replaces the member's previous one and goes to the back of the queue
+        self.bids.retain(|bid| &bid.pubkey != pubkey);
+        self.bids.push(Bid {
+            pubkey: pubkey.clone(),
+            discount,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Move the member at `winner` into the current round's slot in the payout schedule
+    fn schedule_for_current_round(&mut self, winner: usize, scheduled_index: usize) {
+        let winner_round = self.members[winner].payout_round;
+        self.members[scheduled_index].payout_round = winner_round;
+        self.members[winner].payout_round = self.current_round;
+    }
+
+    /// Seats in the lottery draw: unpaid members in schedule order; members who
+    /// lost their turn only once nobody else is left
+    fn lottery_candidates(&self) -> Vec<usize> {
+        let mut candidates: Vec<usize> = (0..self.members.len())
+            .filter(|&i| !self.members[i].has_received_payout)
+            .collect();
+        candidates.sort_by_key(|&i| self.members[i].payout_round);
+        if candidates
+            .iter()
+            .any(|&i| !self.is_skipped(&self.members[i]))
+        {
+            candidates.retain(|&i| !self.is_skipped(&self.members[i]));
+        }
+        candidates
+    }
+
+    /// Draw a lottery winner's position among `candidates` for the current round
+    fn lottery_draw(&self, seed: &[u8; 32], candidates: usize) -> usize {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"charmcircle/lottery");
+        hasher.update(self.circle_id);
+        hasher.update(self.current_round.to_le_bytes());
+        hasher.update(seed);
+        let digest = hasher.finalize();
+
+        let mut draw = [0u8; 8];
+        draw.copy_from_slice(&digest[..8]);
+        (u64::from_le_bytes(draw) % candidates as u64) as usize
+    }
+
+    /// Execute payout for the current round, to the member chosen by the payout policy.
+    /// Lottery circles need a `seed`; the winner is swapped into the current round's slot.
+    pub fn execute_payout(
+        &mut self,
+        timestamp: u64,
+        seed: Option<[u8; 32]>,
+    ) -> Result<(PubKey, Satoshis), String> {
+        self.require_phase(CirclePhase::Active, "execute payouts")?;
+
+        if timestamp < self.round_deadline() {
+            return Err(format!(
+                "Round {} payout not due until {}",
+                self.current_round,
+                self.round_deadline()
+            ));
+        }
+
+        if !self.is_round_settled() {
+            return Err("Round is not fully funded yet".to_string());
+        }
+
+        let scheduled_index = self
+            .payout_index_for_round(self.current_round)
+            .ok_or_else(|| format!("No member scheduled for round {}", self.current_round))?;
+
+        if self.members[scheduled_index].has_received_payout {
+            return Err("Member has already received payout".to_string());
+        }
+
+        let fee = self.fee_for(self.current_pool)?;
+        let fees_collected = self
+            .fees_collected
+            .checked_add(fee)
+            .ok_or("Fees collected overflow")?;
+
+        // Update state hash before transitioning
+        let prev_state_hash = self.state_hash();
+
+        let payout_index = match self.payout_policy {
+            PayoutPolicy::Fixed | PayoutPolicy::Seniority => scheduled_index,
+            PayoutPolicy::Lottery => {
+                let seed = seed.ok_or("Lottery payout requires a seed")?;
+                let candidates = self.lottery_candidates();
+                let winner = candidates[self.lottery_draw(&seed, candidates.len())];
+                self.schedule_for_current_round(winner, scheduled_index);
+                winner
+            }
+            PayoutPolicy::Auction => {
+                // Highest discount wins, earliest bid breaks ties; without bids
+                // the scheduled seat takes the full pot
+                let winner = self
+                    .bids
+                    .iter()
+                    .rev()
+                    .max_by_key(|bid| bid.discount)
+                    .and_then(|bid| self.bidding_seat(&bid.pubkey))
+                    .unwrap_or(scheduled_index);
+                self.schedule_for_current_round(winner, scheduled_index);
+                winner
+            }
+        };
+
+        self.prev_state_hash = prev_state_hash;
+
+        // The fee comes off the top of the pot
+        let mut payout_amount = self.current_pool - fee;
+        self.members[payout_index].fee_paid = fee;
+        self.fees_collected = fees_collected;
+
+        // Auction discount is split evenly among all other members; the rounding
+        // remainder stays with the winner
+        if self.payout_policy == PayoutPolicy::Auction {
+            let recipient = &self.members[payout_index].pubkey;
+            // A pot reduced by defaults or fees caps the discount
+            let bid = self
+                .bids
+                .iter()
+                .find(|bid| &bid.pubkey == recipient)
+                .map_or(0, |bid| bid.discount)
+                .min(payout_amount);
+            let others = self.members.len() as Satoshis - 1;
+            let share = bid.checked_div(others).unwrap_or(0);
+
+            for (index, member) in self.members.iter_mut().enumerate() {
+                if index != payout_index {
+                    member.auction_dividends += share;
+                }
+            }
+            self.members[payout_index].auction_discount = share * others;
+            payout_amount -= share * others;
+            self.bids.clear();
+        }
+
+        // A seat that lost its turn by defaulting takes no more than it put in;
+        // the rest is split evenly among the other seats, the remaining sats one
+        // each in joining order
+        let seat = &self.members[payout_index];
+        if self.is_skipped(seat) {
+            let cap = Self::paid_in(seat).saturating_sub(Self::paid_out(seat));
+            let forfeited = payout_amount.saturating_sub(cap);
+            let others = self.members.len() as Satoshis - 1;
+            let mut remainder = forfeited % others;
+            for (index, member) in self.members.iter_mut().enumerate() {
+                if index != payout_index {
+                    let extra = remainder.min(1);
+                    remainder -= extra;
+                    member.auction_dividends += forfeited / others + extra;
+                }
+            }
+            self.members[payout_index].auction_discount += forfeited;
+            payout_amount -= forfeited;
+        }
+
+        let member = &mut self.members[payout_index];
+        let recipient = member.pubkey.clone();
+
+        member.has_received_payout = true;
+        member.payout_amount = payout_amount;
+
+        // Reset pool and prepare for next round
+        self.current_pool = 0;
+        self.current_round += 1;
+        self.current_payout_index = self.payout_index_for_round(self.current_round).unwrap_or(0);
+        self.round_started_at = timestamp;
+
+        // Check if circle is complete; remaining collateral is returned on settlement.
+        // Otherwise the new round opens with whatever seats prepaid for it.
+        if self.current_round >= self.total_rounds {
+            self.is_complete = true;
+            self.phase = CirclePhase::Settling;
+        } else {
+            self.apply_prepaid_credits();
+        }
+
+        Ok((recipient, payout_amount))
+    }
+
+    /// Satoshis a seat has put into the pools so far: its contributions plus
+    /// whatever its collateral covered
+    fn paid_in(seat: &Member) -> Satoshis {
+        seat.contribution_history
+            .iter()
+            .map(|c| c.amount)
+            .chain(seat.defaults.iter().map(|d| d.covered))
+            .sum()
+    }
+
+    /// Satoshis a seat has taken out of the pools so far: its payout and dividends
+    fn paid_out(seat: &Member) -> Satoshis {
+        seat.payout_amount + seat.auction_dividends
+    }
+
+    /// Distinct member keys, in joining order
+    fn member_keys(&self) -> Vec<&PubKey> {
+        let mut keys: Vec<&PubKey> = Vec::new();
+        for member in &self.members {
+            if !keys.contains(&&member.pubkey) {
+                keys.push(&member.pubkey);
+            }
+        }
+        keys
+    }
+
+    /// Distinct members whose approval is needed to cancel the circle
+    pub fn cancel_approvals_needed(&self) -> usize {
+        let keys = self.member_keys().len();
+        match self.cancel_quorum as usize {
+            0 => keys,
+            quorum => quorum.min(keys),
+        }
+    }
+
+    /// Distinct members whose approval is needed to pay out a round: a majority,
+    /// so no single member can attest that a round is due
+    pub fn payout_approvals_needed(&self) -> usize {
+        self.member_keys().len() / 2 + 1
+    }
+
+    /// Fail unless `approvals` are at least `needed` distinct members
+    fn check_approvals(
+        &self,
+        approvals: &[PubKey],
+        needed: usize,
+        operation: &str,
+    ) -> Result<(), String> {
+        for (index, approver) in approvals.iter().enumerate() {
+            if !self.members.iter().any(|m| &m.pubkey == approver) {
+                return Err(format!("{} approved by a non-member", operation));
+            }
+            if approvals[..index].contains(approver) {
+                return Err(format!("Duplicate {} approval", operation.to_lowercase()));
+            }
+        }
+
+        if approvals.len() < needed {
+            return Err(format!(
+                "{} needs {} member approvals, got {}",
+                operation,
+                needed,
+                approvals.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Split `pool` among the seats, pro rata to what each is owed: what it paid
+    /// in minus what it took out, if positive. Shares are rounded down and the
+    /// remaining sats go one each to the owed seats in joining order.
+    pub fn cancellation_refunds(&self, pool: Satoshis) -> Result<Vec<Satoshis>, String> {
+        let owed: Vec<Satoshis> = self
+            .members
+            .iter()
+            .map(|m| Self::paid_in(m).saturating_sub(Self::paid_out(m)))
+            .collect();
+        let total_owed: u128 = owed.iter().map(|&o| o as u128).sum();
+
+        if pool == 0 {
+            return Ok(owed.iter().map(|_| 0).collect());
+        }
+        if total_owed == 0 {
+            return Err("No member is owed the remaining pool".to_string());
+        }
+
+        let mut refunds: Vec<Satoshis> = owed
+            .iter()
+            .map(|&o| (pool as u128 * o as u128 / total_owed) as Satoshis)
+            .collect();
+        let mut remainder = pool - refunds.iter().sum::<Satoshis>();
+        for (refund, _) in refunds.iter_mut().zip(&owed).filter(|(_, &o)| o > 0) {
+            if remainder == 0 {
+                break;
+            }
+            *refund += 1;
+            remainder -= 1;
+        }
+
+        Ok(refunds)
+    }
+
+    /// Cancel a circle that has not completed: refund the pool pro rata to what
+    /// each seat is owed, and return all remaining collateral and prepaid escrow.
+    /// `approvals` must be distinct members reaching the cancel quorum.
+    pub fn cancel(&mut self, approvals: &[PubKey]) -> Result<(), String> {
+        if !matches!(self.phase, CirclePhase::Forming | CirclePhase::Active) {
+            return Err(format!("Cannot cancel while circle is {:?}", self.phase));
+        }
+
+        self.check_approvals(approvals, self.cancel_approvals_needed(), "Cancellation")?;
+
+        let refunds = self.cancellation_refunds(self.current_pool)?;
+
+        self.prev_state_hash = self.state_hash();
+
+        for (member, refund) in self.members.iter_mut().zip(refunds) {
+            member.refund = refund;
+            member.collateral = 0;
+            member.prepaid = 0;
+        }
+        self.current_pool = 0;
+        self.bids.clear();
+        self.phase = CirclePhase::Cancelled;
+
+        Ok(())
+    }
+
+    /// Summary of a circle whose rounds are all paid out, settled at `timestamp`
+    pub fn settlement_summary(&self, timestamp: u64) -> Settlement {
+        let mut positions: Vec<NetPosition> = Vec::new();
+        for seat in &self.members {
+            let index = match positions.iter().position(|p| p.pubkey == seat.pubkey) {
+                Some(index) => index,
+                None => {
+                    positions.push(NetPosition {
+                        pubkey: seat.pubkey.clone(),
+                        paid_in: 0,
+                        paid_out: 0,
+                    });
+                    positions.len() - 1
+                }
+            };
+            positions[index].paid_in += Self::paid_in(seat);
+            positions[index].paid_out += Self::paid_out(seat);
+        }
+
+        Settlement {
+            total_paid_in: positions.iter().map(|p| p.paid_in).sum(),
+            total_paid_out: positions.iter().map(|p| p.paid_out).sum(),
+            fees_paid: self.fees_collected,
+            collateral_returned: self.members.iter().map(|m| m.collateral).sum(),
+            positions,
+            settled_at: timestamp,
+        }
+    }
+
+    /// Settle a circle whose rounds are all paid out: record the final summary
+    /// and return all remaining collateral. The circle is then complete for good.
+    pub fn settle(&mut self, timestamp: u64) -> Result<(), String> {
+        self.require_phase(CirclePhase::Settling, "settle")?;
+
+        if timestamp < self.round_started_at {
+            return Err("Settlement predates the final payout".to_string());
+        }
+
+        self.prev_state_hash = self.state_hash();
+        self.settlement = Some(self.settlement_summary(timestamp));
+        for member in &mut self.members {
+            member.collateral = 0;
+        }
+        self.phase = CirclePhase::Complete;
+
+        Ok(())
+    }
+
+    /// Start a new cycle of this settled circle, funded by `funding_outpoint` (the
+    /// settled circle's own UTXO when renewed on-chain). Seats of `members` carry
+    /// over with fresh histories and locked collateral; everyone else is dropped.
+    /// The new circle is forming, with the same terms, linked to this one.
+    pub fn renew(
+        &self,
+        funding_outpoint: &UtxoId,
+        created_at: u64,
+        members: &[PubKey],
+        order: RenewalOrder,
+    ) -> Result<Self, String> {
+        self.require_phase(CirclePhase::Complete, "renew")?;
+
+        if members.is_empty() {
+            return Err("A renewed circle needs at least one member".to_string());
+        }
+        for (index, pubkey) in members.iter().enumerate() {
+            if !self.members.iter().any(|m| &m.pubkey == pubkey) {
+                return Err("Renewing member was not in the completed circle".to_string());
+            }
+            if members[..index].contains(pubkey) {
+                return Err("Member listed twice for renewal".to_string());
+            }
+        }
+
+        if self.payout_policy == PayoutPolicy::Seniority && order != RenewalOrder::Keep {
+            return Err("Seniority circles keep their payout order".to_string());
+        }
+
+        let mut next = Self::new(
+            Self::derive_circle_id(funding_outpoint),
+            self.contribution_per_round,
+            self.round_duration,
+            created_at,
+            self.min_members,
+            self.max_members,
+        );
+        next.collateral_per_member = self.collateral_per_member;
+        next.grace_period = self.grace_period;
+        next.payout_policy = self.payout_policy;
+        next.default_policy = self.default_policy;
+        next.fee = self.fee.clone();
+        next.cancel_quorum = self.cancel_quorum;
+        next.predecessor = Some(Predecessor {
+            circle_id: self.circle_id,
+            final_state_hash: self.state_hash(),
+        });
+
+        // Renewed seats keep their joining order; payout order follows `order`
+        let seats: Vec<&Member> = self
+            .members
+            .iter()
+            .filter(|m| members.contains(&m.pubkey))
+            .collect();
+        let mut schedule: Vec<usize> = (0..seats.len()).collect();
+        match order {
+            RenewalOrder::Keep if self.payout_policy == PayoutPolicy::Seniority => {
+                schedule.sort_by_key(|&i| seats[i].joined_at)
+            }
+            RenewalOrder::Keep => schedule.sort_by_key(|&i| seats[i].payout_round),
+            RenewalOrder::Rotate => {
+                schedule.sort_by_key(|&i| seats[i].payout_round);
+                schedule.rotate_left(1);
+            }
+            RenewalOrder::Reshuffle { seed } => {
+                use sha2::{Digest, Sha256};
+
+                schedule.sort_by_cached_key(|&i| {
+                    let mut hasher = Sha256::new();
+                    hasher.update(b"charmcircle/renew");
+                    hasher.update(next.circle_id);
+                    hasher.update(seed);
+                    hasher.update((i as u32).to_le_bytes());
+                    hasher.finalize()
+                });
+            }
+        }
+
+        for seat in &seats {
+            next.members.push(Member {
+                pubkey: seat.pubkey.clone(),
+                contribution_amount: self.contribution_per_round,
+                contribution_history: Vec::new(),
+                has_received_payout: false,
+                payout_round: 0,
+                joined_at: seat.joined_at,
+                auction_discount: 0,
+                auction_dividends: 0,
+                collateral: self.collateral_per_member,
+                defaults: Vec::new(),
+                fee_paid: 0,
+                payout_amount: 0,
+                refund: 0,
+                prepaid: 0,
+            });
+        }
+        for (round, index) in schedule.into_iter().enumerate() {
+            next.members[index].payout_round = round as u32;
+        }
+        next.total_rounds = next.members.len() as u32;
+        next.current_payout_index = next.payout_index_for_round(0).unwrap_or(0);
+
+        Ok(next)
+    }
+
+    /// Apply an action to this state, exactly as the spell performing it must.
+    /// Returns the payments the transaction must make out of the circle, one per
+    /// recipient key.
+    pub fn apply(&mut self, action: &CircleAction) -> Result<Vec<(PubKey, Satoshis)>, String> {
+        let payments = match action {
+            CircleAction::CreateCircle => Err("Circle already exists".to_string()),
+            CircleAction::Renew { .. } => {
+                Err("Renewing creates a new circle; use CircleState::renew".to_string())
+            }
+            CircleAction::RemoveMember { pubkey, .. } => {
+                let collateral = self.remove_member(pubkey)?;
+                Ok(if collateral > 0 {
+                    [(pubkey.clone(), collateral)].into()
+                } else {
+                    Vec::new()
+                })
+            }
+            CircleAction::TransferSeat { from, to, .. } => {
+                self.transfer_seat(from, to.clone()).map(|_| Vec::new())
+            }
+            CircleAction::MarkDefaults {
+                approvals,
+                timestamp,
+            } => self
+                .mark_defaults(approvals, *timestamp)
+                .map(|_| Vec::new()),
+            CircleAction::Settle { pubkey, timestamp } => {
+                self.require_member(pubkey)?;
+                let collateral: Vec<Satoshis> = self.members.iter().map(|m| m.collateral).collect();
+                self.settle(*timestamp)?;
+
+                // Remaining collateral goes back to its members
+                Ok(self
+                    .members
+                    .iter()
+                    .zip(collateral)
+                    .filter(|(_, collateral)| *collateral > 0)
+                    .map(|(m, collateral)| (m.pubkey.clone(), collateral))
+                    .collect())
+            }
+            CircleAction::Cancel { approvals, .. } => {
+                let held: Vec<(Satoshis, Satoshis)> = self
+                    .members
+                    .iter()
+                    .map(|m| (m.collateral, m.prepaid))
+                    .collect();
+                self.cancel(approvals)?;
+
+                // Pool refunds, collateral and prepaid escrow are returned in the
+                // same transaction
+                Ok(self
+                    .members
+                    .iter()
+                    .zip(held)
+                    .flat_map(|(m, (collateral, prepaid))| {
+                        [
+                            (m.pubkey.clone(), m.refund),
+                            (m.pubkey.clone(), collateral),
+                            (m.pubkey.clone(), prepaid),
+                        ]
+                    })
+                    .filter(|(_, amount)| *amount > 0)
+                    .collect())
+            }
+            CircleAction::StartCircle { pubkey, timestamp } => {
+                self.start_circle(pubkey, *timestamp).map(|_| Vec::new())
+            }
+            CircleAction::Join {
+                pubkey,
+                payout_round,
+                joined_at,
+            } => self
+                .add_member(pubkey.clone(), *payout_round, *joined_at)
+                .map(|_| Vec::new()),
+            CircleAction::Contribute {
+                pubkey,
+                amount,
+                timestamp,
+                txid,
+            } => self
+                .record_contribution(pubkey, *amount, *timestamp, *txid)
+                .map(|_| Vec::new()),
+            CircleAction::Prepay {
+                pubkey,
+                amount,
+                timestamp,
+                txid,
+            } => self
+                .prepay(pubkey, *amount, *timestamp, *txid)
+                .map(|_| Vec::new()),
+            CircleAction::Bid {
+                pubkey,
+                discount,
+                timestamp,
+            } => self
+                .place_bid(pubkey, *discount, *timestamp)
+                .map(|_| Vec::new()),
+            CircleAction::Payout {
+                approvals,
+                timestamp,
+                seed,
+            } => {
+                self.check_approvals(approvals, self.payout_approvals_needed(), "Payout")?;
+                let before: Vec<Satoshis> =
+                    self.members.iter().map(|m| m.auction_dividends).collect();
+                let fees_before = self.fees_collected;
+                let mut payments: Vec<(PubKey, Satoshis)> =
+                    [self.execute_payout(*timestamp, *seed)?].into();
+
+                // The fee goes to the fee recipient
+                if let Some(fee) = &self.fee {
+                    if self.fees_collected > fees_before {
+                        payments.push((fee.recipient.clone(), self.fees_collected - fees_before));
+                    }
+                }
+
+                // Auction dividends are paid out in the same transaction
+                payments.extend(
+                    self.members
+                        .iter()
+                        .zip(&before)
+                        .filter(|(m, dividends)| m.auction_dividends > **dividends)
+                        .map(|(m, dividends)| (m.pubkey.clone(), m.auction_dividends - dividends)),
+                );
+
+                Ok(payments)
+            }
+        }?;
+
+        // Everything owed to a key is paid by a single output
+        let mut merged: Vec<(PubKey, Satoshis)> = Vec::new();
+        for (recipient, amount) in payments {
+            match merged.iter_mut().find(|(key, _)| *key == recipient) {
+                Some((_, total)) => *total = total.checked_add(amount).ok_or("Payment overflow")?,
+                None => merged.push((recipient, amount)),
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Satoshis the circle UTXO must hold on behalf of members
+    pub fn locked_value(&self) -> Result<Satoshis, String> {
+        self.members
+            .iter()
+            .try_fold(self.current_pool, |total, m| {
+                total.checked_add(m.collateral)?.checked_add(m.prepaid)
+            })
+            .ok_or_else(|| "Locked value overflow".to_string())
+    }
+
+    /// Fail unless the circle UTXO can lock `amount` more sats
+    fn check_deposit(&self, amount: Satoshis) -> Result<(), String> {
+        self.locked_value()?
+            .checked_add(amount)
+            .map(|_| ())
+            .ok_or_else(|| "Locked value overflow".to_string())
+    }
+
+    /// Contributions present in `next_state` that were not yet recorded in this state.
+    /// Seats are matched by position, so a transferred seat's history is not new;
+    /// histories only grow, so whatever follows a seat's previous records is new
+    /// (identical installments included). Rounds paid from prepaid escrow are not
+    /// contributions made by the transaction, so they are left out.
+    pub fn new_contributions<'a>(
+        &self,
+        next_state: &'a CircleState,
+    ) -> Vec<(&'a PubKey, &'a ContributionRecord)> {
+        next_state
+            .members
+            .iter()
+            .enumerate()
+            .flat_map(|(index, next_member)| {
+                let recorded = self
+                    .members
+                    .get(index)
+                    .map_or(0, |m| m.contribution_history.len());
+
+                next_member
+                    .contribution_history
+                    .iter()
+                    .skip(recorded)
+                    .filter(|c| !c.prepaid)
+                    .map(move |c| (&next_member.pubkey, c))
+            })
+            .collect()
+    }
+
+    /// Validate state transition is allowed
+    pub fn validate_transition(&self, next_state: &CircleState) -> Result<(), String> {
+        // Must be same circle
+        if self.circle_id != next_state.circle_id {
+            return Err("Circle ID mismatch".to_string());
+        }
+
+        // A complete or cancelled circle is final
+        if self.phase.is_terminal() {
+            return Err(format!("Circle is {:?}; its state is final", self.phase));
+        }
+
+        // Phase can only move along allowed edges
+        if !self.phase.can_transition_to(next_state.phase) {
+            return Err(format!(
+                "Invalid phase transition from {:?} to {:?}",
+                self.phase, next_state.phase
+            ));
+        }
+
+        // The circle's terms, membership limits and bond are fixed at creation
+        if self.contribution_per_round != next_state.contribution_per_round {
+            return Err("Cannot change the contribution per round".to_string());
+        }
+
+        if self.round_duration != next_state.round_duration
+            || self.grace_period != next_state.grace_period
+        {
+            return Err("Cannot change the round schedule".to_string());
+        }
+
+        if self.payout_policy != next_state.payout_policy
+            || self.default_policy != next_state.default_policy
+        {
+            return Err("Cannot change the circle's policies".to_string());
+        }
+
+        if self.cancel_quorum != next_state.cancel_quorum {
+            return Err("Cannot change the cancellation terms".to_string());
+        }
+
+        if self.created_at != next_state.created_at {
+            return Err("Cannot change the creation time".to_string());
+        }
+
+        if self.min_members != next_state.min_members || self.max_members != next_state.max_members
+        {
+            return Err("Cannot change membership limits".to_string());
+        }
+
+        if self.collateral_per_member != next_state.collateral_per_member {
+            return Err("Cannot change collateral requirement".to_string());
+        }
+
+        if self.fee != next_state.fee {
+            return Err("Cannot change the circle fee".to_string());
+        }
+
+        if self.predecessor != next_state.predecessor {
+            return Err("Cannot change the circle's predecessor".to_string());
+        }
+
+        if next_state.fees_collected < self.fees_collected {
+            return Err("Fees collected cannot decrease".to_string());
+        }
+
+        // Cannot modify members after start
+        if self.phase != CirclePhase::Forming && self.members.len() != next_state.members.len() {
+            return Err("Cannot change member count after start".to_string());
+        }
+
+        // Must link to this state
+        if next_state.prev_state_hash != self.state_hash() {
+            return Err("Previous state hash mismatch".to_string());
+        }
+
+        // Round can only increment by 0 or 1
+        if next_state.current_round > self.current_round + 1 {
+            return Err("Invalid round progression".to_string());
+        }
+
+        // Pool can only increase or reset to 0, and is emptied by cancelling
+        if next_state.phase == CirclePhase::Cancelled {
+            if next_state.current_pool != 0 {
+                return Err("Cancelled circle must refund its pool".to_string());
+            }
+        } else if next_state.current_round == self.current_round {
+            // Same round: pool should increase
+            if next_state.current_pool < self.current_pool {
+                return Err("Pool cannot decrease within round".to_string());
+            }
+        } else {
+            // New round: pool should reset, holding only what prepaid escrow paid in
+            let credited: Satoshis = next_state
+                .members
+                .iter()
+                .flat_map(|m| &m.contribution_history)
+                .filter(|c| c.round == next_state.current_round && c.prepaid)
+                .map(|c| c.amount)
+                .sum();
+            if next_state.current_pool != credited {
+                return Err("Pool must reset on new round".to_string());
+            }
+
+            // Each seat's escrow pays as much of the new round as it can
+            for (seat, next_seat) in self.members.iter().zip(&next_state.members) {
+                let credit: Satoshis = next_seat
+                    .contribution_history
+                    .iter()
+                    .filter(|c| c.round == next_state.current_round && c.prepaid)
+                    .map(|c| c.amount)
+                    .sum();
+                if credit != seat.prepaid.min(self.contribution_per_round) {
+                    return Err("Prepaid balance not applied to the new round".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify a circle's full history offline: every state must be valid and
+    /// hash-linked to the one before it through an allowed transition
+    pub fn verify_history(history: &[CircleState]) -> Result<(), String> {
+        for state in history {
+            state.validate()?;
+        }
+
+        for (index, pair) in history.windows(2).enumerate() {
+            pair[0]
+                .validate_transition(&pair[1])
+                .map_err(|e| format!("Invalid transition at state {}: {}", index + 1, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Validate the entire state for consistency
+    pub fn validate(&self) -> Result<(), String> {
+        // Check basic constraints
+        // For a valid circle, we must have members (even if current_round is 0)
+        // The only exception is during initial creation before first member is added,
+        // but app_contract handles that case separately
+        if self.members.is_empty() {
+            return Err("Circle has no members".to_string());
+        }
+
+        if self.total_rounds != self.members.len() as u32 {
+            return Err(format!(
+                "Total rounds ({}) must equal number of members ({})",
+                self.total_rounds,
+                self.members.len()
+            ));
+        }
+
+        if self.current_round > self.total_rounds {
+            return Err(format!(
+                "Current round ({}) exceeds total rounds ({})",
+                self.current_round, self.total_rounds
+            ));
+        }
+
+        if self.current_payout_index >= self.members.len() {
+            return Err(format!(
+                "Invalid payout index ({}), must be < {}",
+                self.current_payout_index,
+                self.members.len()
+            ));
+        }
+
+        self.validate_member_limits()?;
+        self.validate_fee()?;
+        self.locked_value()?;
+
+        if self.members.len() > self.max_members as usize {
+            return Err(format!(
+                "Circle has {} members, cap is {}",
+                self.members.len(),
+                self.max_members
+            ));
+        }
+
+        // Started circles must have reached quorum
+        if matches!(
+            self.phase,
+            CirclePhase::Active | CirclePhase::Settling | CirclePhase::Complete
+        ) && self.members.len() < self.min_members as usize
+        {
+            return Err(format!(
+                "Circle started with {} members, quorum is {}",
+                self.members.len(),
+                self.min_members
+            ));
+        }
+
+        if self.is_complete != matches!(self.phase, CirclePhase::Settling | CirclePhase::Complete) {
+            return Err(format!(
+                "Completion flag inconsistent with phase {:?}",
+                self.phase
+            ));
+        }
+
+        // Nothing happens before the circle starts
+        if self.phase == CirclePhase::Forming
+            && (self.current_round != 0
+                || self.current_pool != 0
+                || self
+                    .members
+                    .iter()
+                    .any(|m| !m.contribution_history.is_empty()))
+        {
+            return Err("Forming circle cannot have contributions".to_string());
+        }
+
+        // Payout index must point at the member scheduled for the current round
+        // (a forming circle's schedule may still have gaps)
+        if !self.is_complete
+            && self.phase != CirclePhase::Forming
+            && self.members[self.current_payout_index].payout_round != self.current_round
+        {
+            return Err(format!(
+                "Payout index ({}) does not match the member scheduled for round {}",
+                self.current_payout_index, self.current_round
+            ));
+        }
+
+        // Validate each seat
+        let mut payout_rounds_seen = HashMap::new();
+        let schedule_len = if self.phase == CirclePhase::Forming {
+            self.max_members
+        } else {
+            self.total_rounds
+        };
+        for member in &self.members {
+            // Check payout round is valid
+            if member.payout_round >= schedule_len {
+                return Err("Member has invalid payout round".to_string());
+            }
+
+            // Payout rounds are distinct slots, a permutation of 0..n once started
+            if payout_rounds_seen
+                .insert(member.payout_round, true)
+                .is_some()
+            {
+                return Err(format!(
+                    "Payout round {} assigned to more than one member",
+                    member.payout_round
+                ));
+            }
+
+            // Members are paid exactly in their assigned round
+            if member.has_received_payout != (member.payout_round < self.current_round) {
+                return Err(format!(
+                    "Member scheduled for round {} has payout status inconsistent with round {}",
+                    member.payout_round, self.current_round
+                ));
+            }
+
+            // Validate contribution history: installments for a round add up to
+            // at most the required contribution
+            let mut paid_per_round = HashMap::new();
+            for contrib in &member.contribution_history {
+                if contrib.round >= self.total_rounds {
+                    return Err("Invalid contribution round".to_string());
+                }
+
+                if contrib.amount == 0 {
+                    return Err("Invalid contribution amount".to_string());
+                }
+
+                let paid = paid_per_round.entry(contrib.round).or_insert(0);
+                *paid += contrib.amount;
+                if *paid > self.contribution_per_round {
+                    return Err(format!(
+                        "Contributions for round {} exceed the required {} sats",
+                        contrib.round, self.contribution_per_round
+                    ));
+                }
+            }
+
+            // A missed round is recorded once, for whatever was left unpaid, and
+            // only the collateral policy pays part of it
+            let mut default_rounds = HashMap::new();
+            for default in &member.defaults {
+                if default.round > self.current_round
+                    || default_rounds.insert(default.round, true).is_some()
+                {
+                    return Err("Invalid default round".to_string());
+                }
+
+                let paid = paid_per_round.get(&default.round).copied().unwrap_or(0);
+                if default.shortfall == 0
+                    || default.shortfall != self.contribution_per_round - paid
+                    || default.covered > default.shortfall
+                {
+                    return Err("Invalid default shortfall".to_string());
+                }
+
+                if default.covered > 0 && self.default_policy != DefaultPolicy::Collateral {
+                    return Err("Only the collateral policy covers defaults".to_string());
+                }
+            }
+        }
+
+        // Prepaid escrow covers at most the rounds still to come, and is refunded
+        // when the circle is cancelled
+        let limit = if self.phase.is_terminal() {
+            0
+        } else {
+            self.prepaid_limit()?
+        };
+        for member in &self.members {
+            if member.prepaid > limit {
+                return Err(format!(
+                    "Member has {} sats prepaid, at most {} may be held",
+                    member.prepaid, limit
+                ));
+            }
+        }
+
+        // Collateral is locked in full on joining, only defaults take from it,
+        // and what is left is returned at completion or cancellation
+        for member in &self.members {
+            let covered: Satoshis = member.defaults.iter().map(|d| d.covered).sum();
+            let expected = if self.phase.is_terminal() {
+                0
+            } else {
+                self.collateral_per_member
+                    .checked_sub(covered)
+                    .ok_or_else(|| "Defaults took more than the member's collateral".to_string())?
+            };
+            if member.collateral != expected {
+                return Err(format!(
+                    "Member collateral is {} sats, expected {}",
+                    member.collateral, expected
+                ));
+            }
+        }
+
+        // Members who lost their turn are paid after everyone in good standing
+        if self.default_policy == DefaultPolicy::SkipPayout {
+            let last_in_good_standing = self
+                .members
+                .iter()
+                .filter(|m| !m.has_received_payout && !m.has_defaulted())
+                .map(|m| m.payout_round)
+                .max();
+            let first_skipped = self
+                .members
+                .iter()
+                .filter(|m| self.is_skipped(m))
+                .map(|m| m.payout_round)
+                .min();
+            if let (Some(last), Some(first)) = (last_in_good_standing, first_skipped) {
+                if first < last {
+                    return Err("Defaulter scheduled before a member in good standing".to_string());
+                }
+            }
+
+            if self.bids.iter().any(|bid| {
+                self.bidding_seat(&bid.pubkey).is_none()
+                    && self
+                        .members
+                        .iter()
+                        .any(|m| m.pubkey == bid.pubkey && self.is_skipped(m))
+            }) {
+                return Err("Bid from a member who lost their turn".to_string());
+            }
+        }
+
+        // The schedule must follow the payout policy
+        match self.payout_policy {
+            // Any permutation picked by the members; lottery draws are checked
+            // by app_contract when they happen
+            PayoutPolicy::Fixed | PayoutPolicy::Lottery | PayoutPolicy::Auction => {}
+            PayoutPolicy::Seniority => {
+                // Defaulters may have lost their turn, so only members in good
+                // standing are held to seniority
+                let mut schedule: Vec<&Member> =
+                    self.members.iter().filter(|m| !m.has_defaulted()).collect();
+                schedule.sort_by_key(|m| m.payout_round);
+                if schedule.windows(2).any(|w| w[0].joined_at > w[1].joined_at) {
+                    return Err("Seniority circle must pay earlier joiners first".to_string());
+                }
+            }
+        }
+
+        // Auction accounting: open bids come from unpaid members, and every
+        // discount given up was paid out as dividends to the other members
+        if self.payout_policy != PayoutPolicy::Auction && !self.bids.is_empty() {
+            return Err("Only auction circles can hold bids".to_string());
+        }
+
+        let full_pot = self.full_pot()?;
+        for (index, bid) in self.bids.iter().enumerate() {
+            if !self.members.iter().any(|m| m.pubkey == bid.pubkey) {
+                return Err("Bid from a non-member".to_string());
+            }
+            if !self
+                .members
+                .iter()
+                .any(|m| m.pubkey == bid.pubkey && !m.has_received_payout)
+            {
+                return Err("Bid from a member who has already been paid".to_string());
+            }
+            if bid.discount == 0 || bid.discount >= full_pot {
+                return Err("Invalid bid discount".to_string());
+            }
+            if self.bids[..index].iter().any(|b| b.pubkey == bid.pubkey) {
+                return Err("Member has more than one open bid".to_string());
+            }
+        }
+
+        if self
+            .members
+            .iter()
+            .any(|m| m.auction_discount > 0 && !m.has_received_payout)
+        {
+            return Err("Auction discount recorded for an unpaid member".to_string());
+        }
+
+        let discounts: Satoshis = self.members.iter().map(|m| m.auction_discount).sum();
+        let dividends: Satoshis = self.members.iter().map(|m| m.auction_dividends).sum();
+        if discounts != dividends {
+            return Err(format!(
+                "Auction dividends ({}) do not match discounts given up ({})",
+                dividends, discounts
+            ));
+        }
+
+        // Only a settled circle has a settlement record, and it must match its history
+        match (&self.settlement, self.phase) {
+            (None, CirclePhase::Complete) => {
+                return Err("Complete circle must have a settlement record".to_string());
+            }
+            (Some(_), phase) if phase != CirclePhase::Complete => {
+                return Err(format!("Settlement recorded for a {:?} circle", phase));
+            }
+            (Some(settlement), _) => {
+                let mut expected = self.settlement_summary(settlement.settled_at);
+                expected.collateral_returned = self
+                    .members
+                    .iter()
+                    .map(|m| {
+                        let covered: Satoshis = m.defaults.iter().map(|d| d.covered).sum();
+                        self.collateral_per_member.saturating_sub(covered)
+                    })
+                    .sum();
+                if *settlement != expected {
+                    return Err("Settlement record does not match the circle's history".to_string());
+                }
+            }
+            (None, _) => {}
+        }
+
+        // Fees are taken from paid seats only, never more than the fee on a
+        // full pot, and add up to what the fee recipient collected
+        let max_fee = self.fee_for(full_pot)?;
+        let mut fees_paid: Satoshis = 0;
+        for member in &self.members {
+            if (member.fee_paid > 0 || member.payout_amount > 0) && !member.has_received_payout {
+                return Err("Payout recorded for an unpaid member".to_string());
+            }
+            if member.fee_paid > max_fee {
+                return Err(format!(
+                    "Fee of {} sats exceeds the circle fee of {} sats",
+                    member.fee_paid, max_fee
+                ));
+            }
+            fees_paid = fees_paid
+                .checked_add(member.fee_paid)
+                .ok_or("Fees paid overflow")?;
+        }
+        if fees_paid != self.fees_collected {
+            return Err(format!(
+                "Fees collected ({}) do not match fees paid ({})",
+                self.fees_collected, fees_paid
+            ));
+        }
+
+        // Late flags of the current round must match its deadline
+        for record in self
+            .members
+            .iter()
+            .flat_map(|m| &m.contribution_history)
+            .filter(|c| c.round == self.current_round)
+        {
+            if record.timestamp > self.contribution_cutoff() {
+                return Err("Contribution recorded after the round closed".to_string());
+            }
+            if record.late != (record.timestamp > self.round_deadline()) {
+                return Err("Contribution late flag does not match the round deadline".to_string());
+            }
+        }
+
+        // Validate current pool: this round's contributions plus whatever
+        // collateral covered its defaults
+        let expected_pool: Satoshis = self
+            .members
+            .iter()
+            .map(|m| {
+                let contributed: Satoshis = m
+                    .contribution_history
+                    .iter()
+                    .filter(|c| c.round == self.current_round)
+                    .map(|c| c.amount)
+                    .sum();
+                let covered: Satoshis = m
+                    .defaults
+                    .iter()
+                    .filter(|d| d.round == self.current_round)
+                    .map(|d| d.covered)
+                    .sum();
+                contributed + covered
+            })
+            .sum();
+
+        // A cancelled circle refunded that pool pro rata to what each seat was owed
+        if self.phase == CirclePhase::Cancelled {
+            if self.current_pool != 0 {
+                return Err("Cancelled circle must have an empty pool".to_string());
+            }
+            let refunds = self.cancellation_refunds(expected_pool)?;
+            if self.members.iter().map(|m| m.refund).ne(refunds) {
+                return Err("Cancellation refunds do not match what members were owed".to_string());
+            }
+            return Ok(());
+        }
+
+        if self.members.iter().any(|m| m.refund > 0) {
+            return Err("Refund recorded for a circle that was not cancelled".to_string());
+        }
+
+        if self.current_pool != expected_pool {
+            return Err(format!(
+                "Current pool mismatch. Expected: {}, Got: {}",
+                expected_pool, self.current_pool
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Tag of the app carrying the circle state (`a/${app_id}/${app_vk}`)
+const CIRCLE_APP_TAG: char = 'a';
+
+/// Internal implementation using Result for better error handling
+/// Following the BRO token pattern
+fn app_contract_impl(app: &App, tx: &Transaction, x: &Data, w: &Data) -> Result<()> {
+    match app.tag {
+        NFT => scroll_contract(app, tx),
+        _ => circle_contract(app, tx, x, w),
+    }
+}
+
+/// Decode the circle state carried by `app` in the spent inputs, if any.
+/// A circle lives in a single UTXO, so at most one input may carry it.
+fn input_state(app: &App, tx: &Transaction) -> Result<Option<CircleState>> {
+    let mut states = charm_values(app, tx.ins.iter().map(|(_, charms)| charms));
+    let Some(data) = states.next() else {
+        return Ok(None);
+    };
+    ensure!(
+        states.next().is_none(),
+        "Transaction spends more than one circle state input; circles cannot be merged"
+    );
+
+    data.value()
+        .map(Some)
+        .map_err(|e| anyhow!("Failed to decode previous CircleState: {}", e))
+}
+
+/// Decode the circle state carried by `app` in the transaction outputs.
+/// Exactly one output may carry it, otherwise the circle would be forked.
+fn output_state(app: &App, tx: &Transaction) -> Result<CircleState> {
+    let states: Vec<&Data> = charm_values(app, tx.outs.iter()).collect();
+    ensure!(!states.is_empty(), "No charm data found for app in outputs");
+    ensure!(
+        states.len() == 1,
+        "Transaction creates {} circle state outputs; exactly one is allowed",
+        states.len()
+    );
+
+    states[0]
+        .value()
+        .map_err(|e| anyhow!("Failed to decode new CircleState: {}", e))
+}
+
+/// Find the index of the (single) entry carrying `app` in a list of charms
+fn charm_index<'a>(app: &App, mut charms: impl Iterator<Item = &'a Charms>) -> Option<usize> {
+    charms.position(|c| c.contains_key(app))
+}
+
+/// BTC value of the native output at `index`
+fn coin_value(coins: &Option<Vec<NativeOutput>>, index: usize, what: &str) -> Result<Satoshis> {
+    coins
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction is missing coin {}", what))?
+        .get(index)
+        .map(|coin| coin.amount)
+        .ok_or_else(|| anyhow!("Missing coin {} at index {}", what, index))
+}
+
+/// Check that the real satoshis in the circle UTXO track `locked_value()`.
+/// Whatever the circle holds beyond its locked value (e.g. dust) must stay unchanged,
+/// so a contribution adds exactly its amount and a payout releases exactly the pool.
+fn check_value_conservation(
+    app: &App,
+    tx: &Transaction,
+    prev_state: Option<&CircleState>,
+    new_state: &CircleState,
+) -> Result<()> {
+    let out_index = charm_index(app, tx.outs.iter())
+        .ok_or_else(|| anyhow!("No charm data found for app in outputs"))?;
+    let value_out = coin_value(&tx.coin_outs, out_index, "outputs")?;
+    let locked_out = new_state.locked_value().map_err(|e| anyhow!(e))?;
+    let reserve_out = value_out.checked_sub(locked_out).ok_or_else(|| {
+        anyhow!(
+            "Circle output holds {} sats but must lock {}",
+            value_out,
+            locked_out
+        )
+    })?;
+
+    if let Some(prev_state) = prev_state {
+        let in_index = charm_index(app, tx.ins.iter().map(|(_, charms)| charms))
+            .ok_or_else(|| anyhow!("No charm data found for app in inputs"))?;
+        let value_in = coin_value(&tx.coin_ins, in_index, "inputs")?;
+        let locked_in = prev_state.locked_value().map_err(|e| anyhow!(e))?;
+        let reserve_in = value_in
+            .checked_sub(locked_in)
+            .ok_or_else(|| anyhow!("Circle input holds less than its locked value"))?;
+
+        ensure!(
+            reserve_in == reserve_out,
+            "Circle value changed from {} to {} sats, but locked value changed from {} to {}",
+            value_in,
+            value_out,
+            locked_in,
+            locked_out
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that every payment is made by its own transaction output paying exactly
+/// the amount to the P2WPKH script of the recipient's pubkey
+fn check_payments(tx: &Transaction, payments: &[(PubKey, Satoshis)]) -> Result<()> {
+    let coin_outs = tx
+        .coin_outs
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction is missing coin outputs"))?;
+
+    let mut used = Vec::new();
+    for (recipient, amount) in payments {
+        let script = recipient.p2wpkh_script();
+        let index = coin_outs
+            .iter()
+            .enumerate()
+            .position(|(i, out)| !used.contains(&i) && out.amount == *amount && out.dest == script)
+            .ok_or_else(|| anyhow!("No output pays {} sats to {:?}", amount, recipient))?;
+        used.push(index);
+    }
+
+    Ok(())
+}
+
+/// Validate a circle state transition: decode the previous state from the spent
+/// input (if any) and the new state from the output, then enforce the ROSCA rules
+/// for the action carried in the witness
+fn circle_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> Result<()> {
+    let CircleWitness { action, signatures } = w
+        .value()
+        .map_err(|e| anyhow!("Failed to decode CircleWitness: {}", e))?;
+
+    // Deadlines are checked against the time committed in the public input
+    let inputs: CircleInputs = x
+        .value()
+        .map_err(|e| anyhow!("Failed to decode CircleInputs public input: {}", e))?;
+
+    // Step 1: Extract new state from transaction outputs
+    let new_state = output_state(app, tx)?;
+
+    // Step 2: The new state must be internally consistent
+    new_state
+        .validate()
+        .map_err(|e| anyhow!("Invalid new circle state: {}", e))?;
+
+    // Step 3: If a previous state is being spent, the transition must be allowed.
+    // No previous state means the circle is being created.
+    let Some(prev_state) = input_state(app, tx)? else {
+        ensure!(
+            action == CircleAction::CreateCircle,
+            "Action {:?} requires a previous circle state",
+            action
+        );
+        ensure!(
+            new_state.created_at == inputs.timestamp,
+            "Circle creation time does not match the committed timestamp"
+        );
+        let (funding_outpoint, _) = tx
+            .ins
+            .first()
+            .ok_or_else(|| anyhow!("Circle creation must spend a funding outpoint"))?;
+        new_state
+            .validate_genesis(funding_outpoint)
+            .map_err(|e| anyhow!("Invalid genesis state: {}", e))?;
+        return check_value_conservation(app, tx, None, &new_state);
+    };
+
+    ensure!(
+        action.timestamp() == Some(inputs.timestamp),
+        "Action time does not match the committed timestamp"
+    );
+
+    // Lottery and reshuffle seeds must be committed publicly, so draws can be audited
+    if let CircleAction::Payout {
+        seed: Some(seed), ..
+    }
+    | CircleAction::Renew {
+        order: RenewalOrder::Reshuffle { seed },
+        ..
+    } = &action
+    {
+        ensure!(
+            inputs.seed == Some(*seed),
+            "Seed does not match the public input"
+        );
+    }
+
+    // Members must have authorized the action on this exact state
+    prev_state
+        .verify_authorization(&action, &signatures)
+        .map_err(|e| anyhow!("Action {:?} not authorized: {}", action, e))?;
+
+    // A settled circle is spent only to renew it: the output is a new circle
+    // whose id derives from the settled circle's UTXO
+    if let CircleAction::Renew { members, order, .. } = &action {
+        let in_index = charm_index(app, tx.ins.iter().map(|(_, charms)| charms))
+            .ok_or_else(|| anyhow!("No charm data found for app in inputs"))?;
+        let expected_state = prev_state
+            .renew(&tx.ins[in_index].0, inputs.timestamp, members, *order)
+            .map_err(|e| anyhow!("Action {:?} not allowed: {}", action, e))?;
+        ensure!(
+            expected_state == new_state,
+            "Output state does not match the renewal of the previous circle"
+        );
+        return check_value_conservation(app, tx, Some(&prev_state), &new_state);
+    }
+
+    prev_state
+        .validate_transition(&new_state)
+        .map_err(|e| anyhow!("Invalid state transition: {}", e))?;
+
+    // Step 4: The new state must be exactly the previous state with the action applied
+    let mut expected_state = prev_state.clone();
+    let payments = expected_state
+        .apply(&action)
+        .map_err(|e| anyhow!("Action {:?} not allowed: {}", action, e))?;
+    ensure!(
+        expected_state == new_state,
+        "Output state does not match {:?} applied to previous state",
+        action
+    );
+
+    // Step 5: Real satoshis must move with the pool
+    check_value_conservation(app, tx, Some(&prev_state), &new_state)?;
+
+    // Step 6: Whatever leaves the circle must go to the members it is owed to
+    check_payments(tx, &payments)
+}
+
+/// Validate sealed scroll NFTs: existing scrolls can be moved freely, but new ones
+/// can only be minted as proof of a contribution recorded in the same transaction,
+/// exactly one per contribution, each sent to its contributor
+fn scroll_contract(app: &App, tx: &Transaction) -> Result<()> {
+    if nft_state_preserved(app, tx) {
+        return Ok(());
+    }
+
+    ensure!(
+        charm_values(app, tx.ins.iter().map(|(_, charms)| charms)).count() == 0,
+        "Sealed scrolls cannot be moved and minted in the same transaction"
+    );
+
+    // The circle state app shares identity and vk with the scroll app
+    let circle_app = App {
+        tag: CIRCLE_APP_TAG,
+        identity: app.identity.clone(),
+        vk: app.vk.clone(),
+    };
+    let prev_state = input_state(&circle_app, tx)?
+        .ok_or_else(|| anyhow!("Sealed scrolls can only be minted by a contribution"))?;
+    let new_state = output_state(&circle_app, tx)?;
+    let contributions = prev_state.new_contributions(&new_state);
+
+    let scroll_outs: Vec<usize> = tx
+        .outs
+        .iter()
+        .enumerate()
+        .filter(|(_, charms)| charms.contains_key(app))
+        .map(|(index, _)| index)
+        .collect();
+    ensure!(
+        scroll_outs.len() == contributions.len(),
+        "Expected {} sealed scroll(s) for recorded contributions, found {}",
+        contributions.len(),
+        scroll_outs.len()
+    );
+
+    let coin_outs = tx
+        .coin_outs
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction is missing coin outputs"))?;
+    for (index, (contributor, _)) in scroll_outs.into_iter().zip(contributions) {
+        ensure!(
+            tx.outs[index].get(app).is_some_and(|data| !data.is_empty()),
+            "Sealed scroll data cannot be empty"
+        );
+        let dest = &coin_outs
+            .get(index)
+            .ok_or_else(|| anyhow!("Missing coin output {}", index))?
+            .dest;
+        ensure!(
+            *dest == contributor.p2wpkh_script(),
+            "Sealed scroll in output {} must be sent to the contributor",
+            index
+        );
+    }
+
+    Ok(())
+}
+
+/// Charms covenant contract function for ROSCA
+/// This function validates state transitions for the ROSCA circle
+/// Wrapper that converts Result to bool (BRO token pattern)
+pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
+    app_contract_impl(app, tx, x, w)
+        .map_err(|e| {
+            // In WASM, this won't print, but helps with debugging in tests
+            #[cfg(not(target_arch = "wasm32"))]
+            eprintln!("Contract validation failed: {:?}", e);
+            e
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use charms_sdk::data::{TxId, B32};
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+    use std::collections::BTreeMap;
+
+    fn test_key(n: u8) -> SigningKey {
+        SigningKey::from_bytes(&[n; 32].into()).unwrap()
+    }
+
+    fn test_pubkey(n: u8) -> PubKey {
+        // Compressed SEC1 encoding
+        PubKey(
+            test_key(n)
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec(),
+        )
+    }
+
+    /// Public input committing to the transaction time
+    fn at(timestamp: u64) -> Data {
+        Data::from(&CircleInputs {
+            timestamp,
+            seed: None,
+        })
+    }
+
+    /// Witness for an action that needs no signatures
+    fn unsigned(action: &CircleAction) -> Data {
+        Data::from(&CircleWitness {
+            action: action.clone(),
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Witness for `action` on `prev`, signed by every required (test) member
+    fn signed(prev: &CircleState, action: &CircleAction) -> Data {
+        let hash = prev.signing_hash(action);
+        let signatures = action
+            .signers()
+            .into_iter()
+            .map(|signer| {
+                let n = (1..=10).find(|n| test_pubkey(*n) == *signer).unwrap();
+                let signature: Signature = test_key(n).sign_prehash(&hash).unwrap();
+                signature.to_bytes().to_vec()
+            })
+            .collect();
+
+        Data::from(&CircleWitness {
+            action: action.clone(),
+            signatures,
+        })
+    }
+
+    fn test_app() -> App {
+        App {
+            tag: 'a',
+            identity: B32([7u8; 32]),
+            vk: B32([8u8; 32]),
+        }
+    }
+
+    fn state_charms(app: &App, state: &CircleState) -> Charms {
+        let mut charms = Charms::new();
+        charms.insert(app.clone(), Data::from(state));
+        charms
+    }
+
+    /// Satoshis held by test circle UTXOs on top of their locked value
+    const CIRCLE_DUST: Satoshis = 1_000;
+
+    /// Append an output paying `amount` to `recipient`
+    fn pay(tx: &mut Transaction, recipient: &PubKey, amount: Satoshis) {
+        tx.outs.push(Charms::new());
+        tx.coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(amount, recipient.p2wpkh_script()));
+    }
+
+    fn circle_coin(state: &CircleState) -> NativeOutput {
+        coin_out(state.locked_value().unwrap() + CIRCLE_DUST, vec![0x51])
+    }
+
+    fn funding_outpoint() -> UtxoId {
+        UtxoId(TxId([5u8; 32]), 1)
+    }
+
+    /// Build a transaction spending `prev` (or a plain funding UTXO when creating)
+    /// and creating `next`, with circle UTXO values matching their locked values
+    fn transition_tx(app: &App, prev: Option<&CircleState>, next: &CircleState) -> Transaction {
+        let (input, coin_in) = match prev {
+            Some(state) => (
+                (UtxoId(TxId([9u8; 32]), 0), state_charms(app, state)),
+                circle_coin(state),
+            ),
+            None => (
+                (funding_outpoint(), Charms::new()),
+                coin_out(50_000, vec![0x51]),
+            ),
+        };
+
+        Transaction {
+            ins: vec![input],
+            refs: Vec::new(),
+            outs: vec![state_charms(app, next)],
+            coin_ins: Some(vec![coin_in]),
+            coin_outs: Some(vec![circle_coin(next)]),
+            prev_txs: BTreeMap::new(),
+            app_public_inputs: BTreeMap::new(),
+        }
+    }
+
+    fn scroll_app() -> App {
+        App {
+            tag: NFT,
+            ..test_app()
+        }
+    }
+
+    fn scroll_data() -> Data {
+        let mut scroll = BTreeMap::new();
+        scroll.insert("ticker".to_string(), "SEALED_SCROLL".to_string());
+        Data::from(&scroll)
+    }
+
+    fn coin_out(amount: Satoshis, dest: Vec<u8>) -> NativeOutput {
+        NativeOutput { amount, dest }
+    }
+
+    #[test]
+    fn test_create_circle() {
+        let circle_id = [1u8; 32];
+        let circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890, 2, 10);
+
+        assert_eq!(circle.current_round, 0);
+        assert_eq!(circle.members.len(), 0);
+        assert!(!circle.is_complete);
+    }
+
+    #[test]
+    fn test_add_members() {
+        let circle_id = [1u8; 32];
+        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890, 2, 10);
+
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.add_member(test_pubkey(3), 2, 1234567892).unwrap();
+
+        assert_eq!(circle.members.len(), 3);
+        assert_eq!(circle.total_rounds, 3);
+        circle.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_new_circle_with_one_member() {
+        // This test simulates what serialize_state creates: a new circle with one member
+        let circle_id = [1u8; 32];
+        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890, 2, 10);
+
+        // Add creator as first member (payout_round 0)
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+
+        // Verify state
+        assert_eq!(circle.members.len(), 1);
+        assert_eq!(circle.total_rounds, 1);
+        assert_eq!(circle.current_round, 0);
+        assert_eq!(circle.current_pool, 0);
+        assert_eq!(circle.current_payout_index, 0);
+        assert_eq!(circle.members[0].payout_round, 0);
+
+        // This should pass validation
+        circle
+            .validate()
+            .expect("New circle with one member should validate");
+    }
+
+    #[test]
+    fn test_contribution_and_payout() {
+        let circle_id = [1u8; 32];
+        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890, 2, 10);
+
+        // Add members
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+
+        // Record contributions
+        let txid = [0u8; 32];
+        circle
+            .record_contribution(&test_pubkey(1), 100_000, 1234567900, txid)
+            .unwrap();
+        circle
+            .record_contribution(&test_pubkey(2), 100_000, 1234567901, txid)
+            .unwrap();
+
+        assert!(circle.is_round_fully_funded());
+        assert_eq!(circle.current_pool, 200_000);
+
+        // Execute payout
+        let (recipient, amount) = circle
+            .execute_payout(circle.round_deadline(), None)
+            .unwrap();
+        assert_eq!(recipient, test_pubkey(1));
+        assert_eq!(amount, 200_000);
+        assert_eq!(circle.current_round, 1);
+        assert_eq!(circle.current_pool, 0);
+
+        circle.validate().unwrap();
+    }
+
+    #[test]
+    fn test_state_transition_validation() {
+        let circle_id = [1u8; 32];
+        let mut state1 = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890, 2, 10);
+        state1.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+
+        let mut state2 = state1.clone();
+        state2.current_round = 1;
+        state2.current_pool = 0;
+        state2.prev_state_hash = state1.state_hash();
+
+        // Valid transition
+        state1.validate_transition(&state2).unwrap();
+
+        // Invalid transition - wrong circle ID
+        let mut state3 = state2.clone();
+        state3.circle_id = [2u8; 32];
+        assert!(state1.validate_transition(&state3).is_err());
+    }
+
+    #[test]
+    fn test_contract_enforces_transitions() {
+        let app = test_app();
+        let created = CircleState::create(
+            &funding_outpoint(),
+            100_000,
+            2_592_000,
+            1234567890,
+            2,
+            10,
+            test_pubkey(1),
+        )
+        .unwrap();
+
+        // Creation: no previous state is spent
+        let create = unsigned(&CircleAction::CreateCircle);
+        let tx = transition_tx(&app, None, &created);
+        assert!(app_contract(&app, &tx, &at(1234567890), &create));
+
+        let join = CircleAction::Join {
+            pubkey: test_pubkey(2),
+            payout_round: 1,
+            joined_at: 1234567891,
+        };
+        let mut joined = created.clone();
+        joined.apply(&join).unwrap();
+        let tx = transition_tx(&app, Some(&created), &joined);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567891),
+            &signed(&created, &join)
+        ));
+
+        // Pool inflated without any contribution record
+        let mut inflated = joined.clone();
+        inflated.current_pool = 100_000;
+        let tx = transition_tx(&app, Some(&joined), &inflated);
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567891),
+            &signed(&joined, &join)
+        ));
+
+        // A different circle cannot be spent into this one
+        let mut forked = joined.clone();
+        forked.circle_id = [2u8; 32];
+        let tx = transition_tx(&app, Some(&joined), &forked);
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567891),
+            &signed(&joined, &join)
+        ));
+    }
+
+    #[test]
+    fn test_contract_requires_exact_action_result() {
+        let app = test_app();
+        let mut prev = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        prev.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        prev.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        prev.start_circle(&test_pubkey(1), 1234567895).unwrap();
+
+        let contribute = CircleAction::Contribute {
+            pubkey: test_pubkey(1),
+            amount: 100_000,
+            timestamp: 1234567900,
+            txid: [3u8; 32],
+        };
+        let mut next = prev.clone();
+        next.apply(&contribute).unwrap();
+
+        let tx = transition_tx(&app, Some(&prev), &next);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&prev, &contribute)
+        ));
+
+        // Missing or mismatched witness
+        assert!(!app_contract(&app, &tx, &at(1234567900), &Data::empty()));
+        let payout = CircleAction::Payout {
+            approvals: payout_quorum(&prev),
+            timestamp: 1234567900,
+            seed: None,
+        };
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&prev, &payout)
+        ));
+
+        // Contribution credited to a different member than the witness claims
+        let mut tampered = prev.clone();
+        tampered
+            .record_contribution(&test_pubkey(2), 100_000, 1234567900, [3u8; 32])
+            .unwrap();
+        let tx = transition_tx(&app, Some(&prev), &tampered);
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&prev, &contribute)
+        ));
+
+        // Creation witness cannot be used to spend an existing circle
+        let tx = transition_tx(&app, Some(&prev), &next);
+        let create = unsigned(&CircleAction::CreateCircle);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+    }
+
+    #[test]
+    fn test_scroll_minted_only_for_contribution() {
+        let app = test_app();
+        let scroll = scroll_app();
+        let mut forming = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        forming.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        forming.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        let mut prev = forming.clone();
+        prev.start_circle(&test_pubkey(1), 1234567895).unwrap();
+
+        let mut next = prev.clone();
+        next.record_contribution(&test_pubkey(2), 100_000, 1234567900, [3u8; 32])
+            .unwrap();
+
+        let mut tx = transition_tx(&app, Some(&prev), &next);
+        let mut scroll_out = Charms::new();
+        scroll_out.insert(scroll.clone(), scroll_data());
+        tx.outs.push(scroll_out.clone());
+        tx.coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(546, test_pubkey(2).p2wpkh_script()));
+        assert!(app_contract(&scroll, &tx, &Data::empty(), &Data::empty()));
+
+        // Scroll sent to someone other than the contributor
+        let mut wrong_dest = tx.clone();
+        wrong_dest.coin_outs.as_mut().unwrap()[1].dest = test_pubkey(1).p2wpkh_script();
+        assert!(!app_contract(
+            &scroll,
+            &wrong_dest,
+            &Data::empty(),
+            &Data::empty()
+        ));
+
+        // Two scrolls for a single contribution
+        let mut extra = tx.clone();
+        extra.outs.push(scroll_out.clone());
+        extra
+            .coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(546, test_pubkey(2).p2wpkh_script()));
+        assert!(!app_contract(
+            &scroll,
+            &extra,
+            &Data::empty(),
+            &Data::empty()
+        ));
+
+        // Scroll minted by a transaction that records no contribution
+        let mut no_contribution = transition_tx(&app, Some(&forming), &prev);
+        no_contribution.outs.push(scroll_out);
+        no_contribution.coin_outs = tx.coin_outs.clone();
+        assert!(!app_contract(
+            &scroll,
+            &no_contribution,
+            &Data::empty(),
+            &Data::empty()
+        ));
+    }
+
+    #[test]
+    fn test_contract_conserves_circle_value() {
+        let app = test_app();
+        let mut prev = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        prev.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        prev.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        prev.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        prev.record_contribution(&test_pubkey(1), 100_000, 1234567900, [3u8; 32])
+            .unwrap();
+
+        let contribute = CircleAction::Contribute {
+            pubkey: test_pubkey(2),
+            amount: 100_000,
+            timestamp: 1234567901,
+            txid: [4u8; 32],
+        };
+        let mut funded = prev.clone();
+        funded.apply(&contribute).unwrap();
+        let witness = signed(&prev, &contribute);
+
+        let tx = transition_tx(&app, Some(&prev), &funded);
+        assert!(app_contract(&app, &tx, &at(1234567901), &witness));
+
+        // Contribution recorded but fewer satoshis locked in the circle
+        let mut short = tx.clone();
+        short.coin_outs.as_mut().unwrap()[0].amount -= 1;
+        assert!(!app_contract(&app, &short, &at(1234567901), &witness));
+
+        // Coin amounts are required
+        let mut no_coins = tx.clone();
+        no_coins.coin_ins = None;
+        assert!(!app_contract(&app, &no_coins, &at(1234567901), &witness));
+
+        // Payout must release exactly the pool
+        let due = funded.round_deadline();
+        let payout = CircleAction::Payout {
+            approvals: payout_quorum(&funded),
+            timestamp: due,
+            seed: None,
+        };
+        let mut paid = funded.clone();
+        paid.apply(&payout).unwrap();
+        let witness = signed(&funded, &payout);
+
+        let mut tx = transition_tx(&app, Some(&funded), &paid);
+        pay(&mut tx, &test_pubkey(1), 200_000);
+        assert_eq!(tx.coin_outs.as_ref().unwrap()[0].amount, CIRCLE_DUST);
+        assert!(app_contract(&app, &tx, &at(due), &witness));
+
+        let mut skimmed = tx.clone();
+        skimmed.coin_outs.as_mut().unwrap()[0].amount -= 500;
+        assert!(!app_contract(&app, &skimmed, &at(due), &witness));
+    }
+
+    #[test]
+    fn test_contract_requires_payout_to_recipient() {
+        let app = test_app();
+        let mut funded = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        funded.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        funded.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        funded.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        for n in 1..=2 {
+            funded
+                .record_contribution(&test_pubkey(n), 100_000, 1234567900, [3u8; 32])
+                .unwrap();
+        }
+
+        let due = funded.round_deadline();
+        let payout = CircleAction::Payout {
+            approvals: payout_quorum(&funded),
+            timestamp: due,
+            seed: None,
+        };
+        let mut paid = funded.clone();
+        let payments = paid.apply(&payout).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(1), 200_000)]);
+        let witness = signed(&funded, &payout);
+
+        let mut tx = transition_tx(&app, Some(&funded), &paid);
+        pay(&mut tx, &test_pubkey(1), 200_000);
+        assert!(app_contract(&app, &tx, &at(due), &witness));
+
+        // Pot sent to someone other than the scheduled recipient
+        let mut redirected = tx.clone();
+        redirected.coin_outs.as_mut().unwrap()[1].dest = test_pubkey(2).p2wpkh_script();
+        assert!(!app_contract(&app, &redirected, &at(due), &witness));
+
+        // Recipient paid less than the pool
+        let mut short = tx.clone();
+        short.coin_outs.as_mut().unwrap()[1].amount = 150_000;
+        assert!(!app_contract(&app, &short, &at(due), &witness));
+    }
+
+    #[test]
+    fn test_contract_rejects_forked_circles() {
+        let app = test_app();
+        let mut prev = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        prev.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+
+        let join = CircleAction::Join {
+            pubkey: test_pubkey(2),
+            payout_round: 1,
+            joined_at: 1234567891,
+        };
+        let mut next = prev.clone();
+        next.apply(&join).unwrap();
+        let witness = signed(&prev, &join);
+
+        // Cloning the circle into two state outputs
+        let mut cloned = transition_tx(&app, Some(&prev), &next);
+        cloned.outs.push(state_charms(&app, &next));
+        cloned.coin_outs.as_mut().unwrap().push(circle_coin(&next));
+        assert!(!app_contract(&app, &cloned, &at(1234567891), &witness));
+
+        // Merging two circle state inputs into one output
+        let mut merged = transition_tx(&app, Some(&prev), &next);
+        merged
+            .ins
+            .push((UtxoId(TxId([9u8; 32]), 1), state_charms(&app, &prev)));
+        merged.coin_ins.as_mut().unwrap().push(circle_coin(&prev));
+        assert!(!app_contract(&app, &merged, &at(1234567891), &witness));
+
+        // Creating two circles at once
+        let create = unsigned(&CircleAction::CreateCircle);
+        let genesis = CircleState::create(
+            &funding_outpoint(),
+            100_000,
+            2_592_000,
+            1234567890,
+            2,
+            10,
+            test_pubkey(1),
+        )
+        .unwrap();
+        let tx = transition_tx(&app, None, &genesis);
+        assert!(app_contract(&app, &tx, &at(1234567890), &create));
+        let mut twins = tx.clone();
+        twins.outs.push(state_charms(&app, &genesis));
+        twins
+            .coin_outs
+            .as_mut()
+            .unwrap()
+            .push(circle_coin(&genesis));
+        assert!(!app_contract(&app, &twins, &at(1234567890), &create));
+    }
+
+    #[test]
+    fn test_state_hash_chain() {
+        let mut state = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        state.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        let mut history = vec![state.clone()];
+
+        let actions = [
+            CircleAction::Join {
+                pubkey: test_pubkey(2),
+                payout_round: 1,
+                joined_at: 1234567891,
+            },
+            CircleAction::StartCircle {
+                pubkey: test_pubkey(1),
+                timestamp: 1234567895,
+            },
+            CircleAction::Contribute {
+                pubkey: test_pubkey(1),
+                amount: 100_000,
+                timestamp: 1234567900,
+                txid: [3u8; 32],
+            },
+            CircleAction::Contribute {
+                pubkey: test_pubkey(2),
+                amount: 100_000,
+                timestamp: 1234567901,
+                txid: [4u8; 32],
+            },
+            CircleAction::Payout {
+                approvals: vec![test_pubkey(1), test_pubkey(2)],
+                // Round 0 deadline
+                timestamp: 1234567895 + 2_592_000,
+                seed: None,
+            },
+        ];
+        for action in &actions {
+            let prev_hash = state.state_hash();
+            state.apply(action).unwrap();
+            assert_eq!(state.prev_state_hash, prev_hash);
+            history.push(state.clone());
+        }
+
+        CircleState::verify_history(&history).unwrap();
+
+        // Rewriting an earlier state breaks the chain
+        history[2].members[0].joined_at += 1;
+        assert!(CircleState::verify_history(&history).is_err());
+
+        // The circle's terms cannot be rewritten along the way
+        let retermed: [fn(&mut CircleState); 5] = [
+            |s| s.contribution_per_round += 1,
+            |s| s.grace_period += 1,
+            |s| s.payout_policy = PayoutPolicy::Lottery,
+            |s| s.cancel_quorum = 1,
+            |s| s.created_at -= 1,
+        ];
+        for change in retermed {
+            let mut next = history[1].clone();
+            change(&mut next);
+            assert!(history[0].validate_transition(&next).is_err());
+        }
+
+        // Transition that does not link to its predecessor
+        let mut unlinked = history[1].clone();
+        unlinked.prev_state_hash = [0u8; 32];
+        assert!(history[0].validate_transition(&unlinked).is_err());
+    }
+
+    #[test]
+    fn test_genesis_rules() {
+        let app = test_app();
+        let create = unsigned(&CircleAction::CreateCircle);
+        let genesis = CircleState::create(
+            &funding_outpoint(),
+            100_000,
+            2_592_000,
+            1234567890,
+            2,
+            10,
+            test_pubkey(1),
+        )
+        .unwrap();
+
+        assert_eq!(
+            genesis.circle_id,
+            CircleState::derive_circle_id(&funding_outpoint())
+        );
+        assert_ne!(
+            genesis.circle_id,
+            CircleState::derive_circle_id(&UtxoId(TxId([5u8; 32]), 2))
+        );
+        genesis.validate_genesis(&funding_outpoint()).unwrap();
+
+        let tx = transition_tx(&app, None, &genesis);
+        assert!(app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Caller-chosen circle id
+        let mut chosen_id = genesis.clone();
+        chosen_id.circle_id = [1u8; 32];
+        let tx = transition_tx(&app, None, &chosen_id);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Created with more than one member
+        let mut crowded = genesis.clone();
+        crowded.add_member(test_pubkey(2), 1, 1234567890).unwrap();
+        let tx = transition_tx(&app, None, &crowded);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Created already running
+        let mut started = genesis.clone();
+        started.phase = CirclePhase::Active;
+        let tx = transition_tx(&app, None, &started);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Created with a pre-filled pool
+        let mut prefilled = started.clone();
+        prefilled
+            .record_contribution(&test_pubkey(1), 100_000, 1234567890, [3u8; 32])
+            .unwrap();
+        let tx = transition_tx(&app, None, &prefilled);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Created claiming to renew another circle
+        let mut renewal = genesis.clone();
+        renewal.predecessor = Some(Predecessor {
+            circle_id: [0xAA; 32],
+            final_state_hash: [0xBB; 32],
+        });
+        assert!(renewal.validate_genesis(&funding_outpoint()).is_err());
+        let tx = transition_tx(&app, None, &renewal);
+        assert!(!app_contract(&app, &tx, &at(1234567890), &create));
+
+        // Created already settled
+        let mut settled = genesis.clone();
+        settled.settlement = Some(genesis.settlement_summary(1234567890));
+        assert!(settled.validate_genesis(&funding_outpoint()).is_err());
+    }
+
+    #[test]
+    fn test_contract_requires_member_signatures() {
+        let app = test_app();
+        let mut prev = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        prev.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+
+        let join = CircleAction::Join {
+            pubkey: test_pubkey(2),
+            payout_round: 1,
+            joined_at: 1234567891,
+        };
+        let mut joined = prev.clone();
+        joined.apply(&join).unwrap();
+        let tx = transition_tx(&app, Some(&prev), &joined);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567891),
+            &signed(&prev, &join)
+        ));
+
+        // Enrolled without the new member's signature
+        assert!(!app_contract(&app, &tx, &at(1234567891), &unsigned(&join)));
+
+        // Signed by someone else
+        let hash = prev.signing_hash(&join);
+        let forged: Signature = test_key(3).sign_prehash(&hash).unwrap();
+        let forged = Data::from(&CircleWitness {
+            action: join.clone(),
+            signatures: vec![forged.to_bytes().to_vec()],
+        });
+        assert!(!app_contract(&app, &tx, &at(1234567891), &forged));
+
+        // Credited without the contributor's consent
+        let contribute = CircleAction::Contribute {
+            pubkey: test_pubkey(2),
+            amount: 100_000,
+            timestamp: 1234567900,
+            txid: [3u8; 32],
+        };
+        let mut started = joined.clone();
+        started.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        let mut funded = started.clone();
+        funded.apply(&contribute).unwrap();
+        let tx = transition_tx(&app, Some(&started), &funded);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&started, &contribute)
+        ));
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &unsigned(&contribute)
+        ));
+
+        // Signatures are bound to the state they were made against
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&prev, &contribute)
+        ));
+    }
+
+    #[test]
+    fn test_payout_follows_payout_round() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        // A taken round cannot be claimed; nobody is pushed back
+        assert!(circle
+            .clone()
+            .add_member(test_pubkey(2), 0, 1234567891)
+            .is_err());
+        // Any free round below max_members may be picked, leaving gaps
+        circle.add_member(test_pubkey(2), 5, 1234567891).unwrap();
+        circle.add_member(test_pubkey(3), 1, 1234567892).unwrap();
+
+        let rounds: Vec<u32> = circle.members.iter().map(|m| m.payout_round).collect();
+        assert_eq!(rounds, vec![0, 5, 1]);
+        circle.validate().unwrap();
+
+        // Starting closes the gaps, keeping the order picked
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        let rounds: Vec<u32> = circle.members.iter().map(|m| m.payout_round).collect();
+        assert_eq!(rounds, vec![0, 2, 1]);
+        assert_eq!(circle.current_payout_index, 0);
+        circle.validate().unwrap();
+
+        let mut recipients = Vec::new();
+        for _ in 0..3 {
+            fund_round(&mut circle);
+            let (recipient, amount) = circle
+                .execute_payout(circle.round_deadline(), None)
+                .unwrap();
+            assert_eq!(amount, 300_000);
+            recipients.push(recipient);
+            circle.validate().unwrap();
+        }
+        assert_eq!(
+            recipients,
+            vec![test_pubkey(1), test_pubkey(3), test_pubkey(2)]
+        );
+        assert!(circle.is_complete);
+
+        // Invalid payout round
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        assert!(circle.add_member(test_pubkey(2), 10, 1234567891).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_payout_schedule() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.validate().unwrap();
+
+        // Duplicate payout round
+        let mut duplicate = circle.clone();
+        duplicate.members[1].payout_round = 0;
+        assert!(duplicate.validate().is_err());
+
+        // Paid out of turn
+        let mut out_of_turn = circle.clone();
+        out_of_turn.members[1].has_received_payout = true;
+        out_of_turn.current_round = 1;
+        assert!(out_of_turn.validate().is_err());
+
+        // Round passed without paying the scheduled member
+        let mut skipped = circle.clone();
+        skipped.current_round = 1;
+        skipped.current_payout_index = 1;
+        assert!(skipped.validate().is_err());
+    }
+
+    /// Fund the current round with an on-time contribution from every member
+    fn fund_round(circle: &mut CircleState) {
+        let timestamp = circle.round_started_at;
+        let pubkeys: Vec<PubKey> = circle.members.iter().map(|m| m.pubkey.clone()).collect();
+        for pubkey in &pubkeys {
+            circle
+                .record_contribution(pubkey, circle.contribution_per_round, timestamp, [3u8; 32])
+                .unwrap();
+        }
+    }
+
+    /// The first members, in joining order, making up the payout quorum
+    fn payout_quorum(circle: &CircleState) -> Vec<PubKey> {
+        circle
+            .member_keys()
+            .into_iter()
+            .take(circle.payout_approvals_needed())
+            .cloned()
+            .collect()
+    }
+
+    /// Unseeded payout of the current round, run at its deadline
+    fn due_payout(circle: &CircleState) -> CircleAction {
+        CircleAction::Payout {
+            approvals: payout_quorum(circle),
+            timestamp: circle.round_deadline(),
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn test_seniority_payout_policy() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.payout_policy = PayoutPolicy::Seniority;
+        // Requested payout rounds are ignored; earlier joiners are paid first
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(3), 0, 1234567892).unwrap();
+
+        // Joining times cannot be backdated to jump the queue
+        assert!(circle.clone().add_member(test_pubkey(2), 0, 5).is_err());
+        assert!(circle
+            .clone()
+            .add_member(test_pubkey(2), 0, 1234567891)
+            .is_err());
+        circle.add_member(test_pubkey(2), 0, 1234567895).unwrap();
+        circle.validate().unwrap();
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+
+        let mut recipients = Vec::new();
+        for _ in 0..3 {
+            fund_round(&mut circle);
+            recipients.push(
+                circle
+                    .execute_payout(circle.round_deadline(), None)
+                    .unwrap()
+                    .0,
+            );
+        }
+        assert_eq!(
+            recipients,
+            vec![test_pubkey(1), test_pubkey(3), test_pubkey(2)]
+        );
+        circle.validate().unwrap();
+
+        // A schedule that pays a newer member first is rejected
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 1, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 0, 1234567895).unwrap();
+        circle.validate().unwrap();
+        circle.payout_policy = PayoutPolicy::Seniority;
+        assert!(circle.validate().is_err());
+    }
+
+    #[test]
+    fn test_lottery_payout_policy() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.payout_policy = PayoutPolicy::Lottery;
+        for n in 1..=4 {
+            circle.add_member(test_pubkey(n), 0, 1234567890).unwrap();
+        }
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        circle.validate().unwrap();
+
+        fund_round(&mut circle);
+        assert!(circle.clone().execute_payout(1234567950, None).is_err());
+
+        // Same seed, same winner; every member is eventually paid exactly once
+        let mut replay = circle.clone();
+        let mut recipients = Vec::new();
+        for round in 0..4u8 {
+            if round > 0 {
+                fund_round(&mut circle);
+                fund_round(&mut replay);
+            }
+            let (winner, _) = circle
+                .execute_payout(circle.round_deadline(), Some([round; 32]))
+                .unwrap();
+            let (replayed, _) = replay
+                .execute_payout(replay.round_deadline(), Some([round; 32]))
+                .unwrap();
+            assert_eq!(winner, replayed);
+            circle.validate().unwrap();
+            recipients.push(winner);
+        }
+        for n in 1..=4 {
+            assert!(recipients.contains(&test_pubkey(n)));
+        }
+        assert!(circle.is_complete);
+    }
+
+    #[test]
+    fn test_contract_checks_lottery_seed() {
+        let app = test_app();
+        let mut funded = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        funded.payout_policy = PayoutPolicy::Lottery;
+        for n in 1..=3 {
+            funded.add_member(test_pubkey(n), 0, 1234567890).unwrap();
+        }
+        funded.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        fund_round(&mut funded);
+
+        // Member 3 is still in the draw but does not sign; the majority of
+        // members 1 and 2 approves the seed without them
+        let seed = [42u8; 32];
+        let due = funded.round_deadline();
+        let payout = CircleAction::Payout {
+            approvals: vec![test_pubkey(1), test_pubkey(2)],
+            timestamp: due,
+            seed: Some(seed),
+        };
+        let mut paid = funded.clone();
+        let payments = paid.apply(&payout).unwrap();
+        let mut tx = transition_tx(&app, Some(&funded), &paid);
+        pay(&mut tx, &payments[0].0, payments[0].1);
+
+        let witness = signed(&funded, &payout);
+        let committed = |seed| {
+            Data::from(&CircleInputs {
+                timestamp: due,
+                seed,
+            })
+        };
+        assert!(app_contract(&app, &tx, &committed(Some(seed)), &witness));
+
+        // Seed not committed, or a different seed committed
+        assert!(!app_contract(&app, &tx, &committed(None), &witness));
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &committed(Some([7u8; 32])),
+            &witness
+        ));
+
+        // The draw still needs the quorum, not just the caller
+        let hash = funded.signing_hash(&payout);
+        let signature: Signature = test_key(1).sign_prehash(&hash).unwrap();
+        let caller_only = Data::from(&CircleWitness {
+            action: payout.clone(),
+            signatures: vec![signature.to_bytes().to_vec()],
+        });
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &committed(Some(seed)),
+            &caller_only
+        ));
+    }
+
+    #[test]
+    fn test_auction_rounds() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        circle.payout_policy = PayoutPolicy::Auction;
+        for n in 1..=3 {
+            circle.add_member(test_pubkey(n), 0, 1234567890).unwrap();
+        }
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+
+        // Round 0: member 3 outbids member 2
+        fund_round(&mut circle);
+        circle
+            .place_bid(&test_pubkey(2), 20_000, 1234567901)
+            .unwrap();
+        circle
+            .place_bid(&test_pubkey(3), 30_000, 1234567902)
+            .unwrap();
+        assert!(circle
+            .place_bid(&test_pubkey(1), 300_000, 1234567903)
+            .is_err());
+        circle.validate().unwrap();
+
+        let payments = circle.apply(&due_payout(&circle)).unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                (test_pubkey(3), 270_000),
+                (test_pubkey(1), 15_000),
+                (test_pubkey(2), 15_000),
+            ]
+        );
+        assert_eq!(circle.members[2].auction_discount, 30_000);
+        assert_eq!(circle.members[0].auction_dividends, 15_000);
+        assert!(circle.bids.is_empty());
+        circle.validate().unwrap();
+
+        // Paid members can no longer bid
+        assert!(circle
+            .place_bid(&test_pubkey(3), 10_000, circle.round_started_at)
+            .is_err());
+
+        // Round 1: no bids, the scheduled member takes the full pot
+        fund_round(&mut circle);
+        let payments = circle.apply(&due_payout(&circle)).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(2), 300_000)]);
+
+        // Round 2: the last unpaid member
+        fund_round(&mut circle);
+        let payments = circle.apply(&due_payout(&circle)).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(1), 300_000)]);
+        assert!(circle.is_complete);
+        circle.validate().unwrap();
+
+        // Dividends that were never backed by a discount
+        let mut inflated = circle.clone();
+        inflated.members[1].auction_dividends += 1;
+        assert!(inflated.validate().is_err());
+    }
+
+    #[test]
+    fn test_contract_pays_auction_dividends() {
+        let app = test_app();
+        let mut funded = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        funded.payout_policy = PayoutPolicy::Auction;
+        for n in 1..=3 {
+            funded.add_member(test_pubkey(n), 0, 1234567890).unwrap();
+        }
+        funded.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        fund_round(&mut funded);
+
+        let bid = CircleAction::Bid {
+            pubkey: test_pubkey(2),
+            discount: 40_001,
+            timestamp: 1234567901,
+        };
+        let mut bid_placed = funded.clone();
+        bid_placed.apply(&bid).unwrap();
+        let tx = transition_tx(&app, Some(&funded), &bid_placed);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567901),
+            &signed(&funded, &bid)
+        ));
+        assert!(!app_contract(&app, &tx, &at(1234567901), &unsigned(&bid)));
+
+        let payout = due_payout(&bid_placed);
+        let due = bid_placed.round_deadline();
+        let mut paid = bid_placed.clone();
+        let payments = paid.apply(&payout).unwrap();
+        // 40_001 split between two members: 20_000 each, 1 sat stays with the winner
+        assert_eq!(payments[0], (test_pubkey(2), 260_000));
+
+        let mut tx = transition_tx(&app, Some(&bid_placed), &paid);
+        for (recipient, amount) in &payments {
+            pay(&mut tx, recipient, *amount);
+        }
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(due),
+            &signed(&bid_placed, &payout)
+        ));
+
+        // Dividend output missing
+        let mut no_dividends = tx.clone();
+        no_dividends.outs.pop();
+        no_dividends.coin_outs.as_mut().unwrap().pop();
+        assert!(!app_contract(
+            &app,
+            &no_dividends,
+            &at(due),
+            &signed(&bid_placed, &payout)
+        ));
+    }
+
+    #[test]
+    fn test_circle_lifecycle_phases() {
+        let app = test_app();
+        let mut forming = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
+        forming.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        forming.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        assert_eq!(forming.phase, CirclePhase::Forming);
+
+        // Nothing runs before the circle is started
+        assert!(forming
+            .clone()
+            .record_contribution(&test_pubkey(1), 100_000, 1234567892, [3u8; 32])
+            .is_err());
+        assert!(forming.clone().execute_payout(1234567892, None).is_err());
+
+        // Only the creator can start the circle
+        assert!(forming
+            .clone()
+            .start_circle(&test_pubkey(2), 1234567895)
+            .is_err());
+        let start = CircleAction::StartCircle {
+            pubkey: test_pubkey(1),
+            timestamp: 1234567895,
+        };
+        let mut active = forming.clone();
+        active.apply(&start).unwrap();
+        assert_eq!(active.phase, CirclePhase::Active);
+        assert_eq!(active.round_started_at, 1234567895);
+
+        let tx = transition_tx(&app, Some(&forming), &active);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567895),
+            &signed(&forming, &start)
+        ));
+        assert!(!app_contract(&app, &tx, &at(1234567895), &unsigned(&start)));
+
+        // Membership is frozen once active, even before any contribution
+        assert!(active
+            .clone()
+            .add_member(test_pubkey(3), 2, 1234567896)
+            .is_err());
+        assert!(active
+            .clone()
+            .start_circle(&test_pubkey(1), 1234567896)
+            .is_err());
+
+        // Phases cannot go backwards
+        let mut reopened = active.clone();
+        reopened.phase = CirclePhase::Forming;
+        reopened.prev_state_hash = active.state_hash();
+        assert!(active.validate_transition(&reopened).is_err());
+
+        // The last payout completes the circle, and settling it makes that final
+        let mut circle = active.clone();
+        for _ in 0..2 {
+            fund_round(&mut circle);
+            circle
+                .execute_payout(circle.round_deadline(), None)
+                .unwrap();
+        }
+        assert_eq!(circle.phase, CirclePhase::Settling);
+        circle.validate().unwrap();
+        assert!(circle.clone().execute_payout(1234567999, None).is_err());
+
+        let mut inconsistent = circle.clone();
+        inconsistent.phase = CirclePhase::Active;
+        assert!(inconsistent.validate().is_err());
+    }
+
+    #[test]
+    fn test_membership_limits() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 3, 4);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+
+        // Quorum not reached yet
+        assert!(circle
+            .clone()
+            .start_circle(&test_pubkey(1), 1234567895)
+            .is_err());
+
+        circle.add_member(test_pubkey(3), 2, 1234567892).unwrap();
+        circle.add_member(test_pubkey(4), 3, 1234567893).unwrap();
+        circle.validate().unwrap();
+
+        // Cap reached
+        assert!(circle
+            .clone()
+            .add_member(test_pubkey(5), 4, 1234567894)
+            .is_err());
+        let mut overfull = circle.clone();
+        overfull.max_members = 3;
+        assert!(overfull.validate().is_err());
+
+        let mut started = circle.clone();
+        started.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        started.validate().unwrap();
+
+        // Limits cannot be relaxed later
+        let mut relaxed = circle.clone();
+        relaxed.max_members = 10;
+        relaxed.prev_state_hash = circle.state_hash();
+        assert!(circle.validate_transition(&relaxed).is_err());
+
+        // Invalid limits are refused at creation
+        assert!(CircleState::create(
+            &funding_outpoint(),
+            100_000,
+            2_592_000,
+            1234567890,
+            1,
+            10,
+            test_pubkey(1)
+        )
+        .is_err());
+        assert!(CircleState::create(
+            &funding_outpoint(),
+            100_000,
+            2_592_000,
+            1234567890,
+            5,
+            4,
+            test_pubkey(1)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_round_deadlines() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.grace_period = 200;
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        assert_eq!(circle.round_deadline(), 1234569000);
+        assert_eq!(circle.contribution_cutoff(), 1234569200);
+
+        // Before the round started, or after the grace period
+        assert!(circle
+            .clone()
+            .record_contribution(&test_pubkey(1), 100_000, 1234567999, [3u8; 32])
+            .is_err());
+        assert!(circle
+            .clone()
+            .record_contribution(&test_pubkey(1), 100_000, 1234569201, [3u8; 32])
+            .is_err());
+
+        // On time at the deadline, late during the grace period
+        circle
+            .record_contribution(&test_pubkey(1), 100_000, 1234569000, [3u8; 32])
+            .unwrap();
+        let contribute = CircleAction::Contribute {
+            pubkey: test_pubkey(2),
+            amount: 100_000,
+            timestamp: 1234569100,
+            txid: [4u8; 32],
+        };
+        let mut funded = circle.clone();
+        funded.apply(&contribute).unwrap();
+        assert!(!funded.members[0].contribution_history[0].late);
+        assert!(funded.members[1].contribution_history[0].late);
+        funded.validate().unwrap();
+
+        let mut unflagged = funded.clone();
+        unflagged.members[1].contribution_history[0].late = false;
+        assert!(unflagged.validate().is_err());
+
+        // The action time must be the committed time
+        let tx = transition_tx(&app, Some(&circle), &funded);
+        let witness = signed(&circle, &contribute);
+        assert!(app_contract(&app, &tx, &at(1234569100), &witness));
+        assert!(!app_contract(&app, &tx, &at(1234568500), &witness));
+        assert!(!app_contract(&app, &tx, &Data::empty(), &witness));
+
+        // Payout runs at the deadline, not before
+        assert!(funded.clone().execute_payout(1234568999, None).is_err());
+        let mut paid = funded.clone();
+        paid.execute_payout(1234569150, None).unwrap();
+        assert_eq!(paid.round_started_at, 1234569150);
+        assert_eq!(paid.round_deadline(), 1234570150);
+        paid.validate().unwrap();
+    }
+
+    /// Active three-member circle with a 1000s round and 100s grace period
+    /// where members 1 and 2 paid round 0 and member 3 did not
+    fn circle_with_missed_contribution(policy: DefaultPolicy, collateral: Satoshis) -> CircleState {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.grace_period = 100;
+        circle.default_policy = policy;
+        circle.collateral_per_member = collateral;
+        for n in 1..=3 {
+            circle
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        for n in 1..=2 {
+            circle
+                .record_contribution(&test_pubkey(n), 100_000, 1234568500, [3u8; 32])
+                .unwrap();
+        }
+        circle
+    }
+
+    #[test]
+    fn test_default_handling() {
+        let mut circle = circle_with_missed_contribution(DefaultPolicy::ReducedPot, 0);
+
+        // One missing contribution blocks the payout until defaults are marked,
+        // which is only possible once the grace period is over
+        let paid = [test_pubkey(1), test_pubkey(2)];
+        assert!(circle.clone().execute_payout(1234569000, None).is_err());
+        assert!(circle.clone().mark_defaults(&paid, 1234569100).is_err());
+        circle.mark_defaults(&paid, 1234569101).unwrap();
+        assert_eq!(circle.members[2].defaults[0].shortfall, 100_000);
+        assert!(circle.clone().mark_defaults(&paid, 1234569102).is_err());
+        assert!(circle
+            .clone()
+            .record_contribution(&test_pubkey(3), 100_000, 1234569050, [3u8; 32])
+            .is_err());
+        circle.validate().unwrap();
+
+        // Reduced pot: the scheduled member gets what was collected
+        let (recipient, amount) = circle.execute_payout(1234569101, None).unwrap();
+        assert_eq!((recipient, amount), (test_pubkey(1), 200_000));
+        circle.validate().unwrap();
+
+        // Skip payout: the defaulter loses their turn
+        let mut circle = circle_with_missed_contribution(DefaultPolicy::SkipPayout, 0);
+        circle.members[0].payout_round = 2;
+        circle.members[2].payout_round = 0;
+        circle.current_payout_index = 2;
+        circle.validate().unwrap();
+        circle.mark_defaults(&paid, 1234569101).unwrap();
+        assert_eq!(circle.members[2].payout_round, 2);
+        circle.validate().unwrap();
+        let (recipient, amount) = circle.execute_payout(1234569101, None).unwrap();
+        assert_eq!((recipient, amount), (test_pubkey(2), 200_000));
+
+        circle.validate().unwrap();
+        let mut jumped = circle.clone();
+        jumped.members[0].payout_round = 2;
+        jumped.members[2].payout_round = 1;
+        jumped.current_payout_index = 2;
+        assert!(jumped.validate().is_err());
+
+        // Collateral: the shortfall is taken from the defaulter's bond
+        let mut circle = circle_with_missed_contribution(DefaultPolicy::Collateral, 60_000);
+        circle.mark_defaults(&paid, 1234569101).unwrap();
+        assert_eq!(circle.members[2].collateral, 0);
+        assert_eq!(circle.members[2].defaults[0].covered, 60_000);
+        assert_eq!(circle.current_pool, 260_000);
+        circle.validate().unwrap();
+
+        let mut inflated = circle.clone();
+        inflated.current_pool += 40_000;
+        assert!(inflated.validate().is_err());
+    }
+
+    #[test]
+    fn test_skipped_defaulter_takes_what_they_paid_in() {
+        let mut circle = circle_with_missed_contribution(DefaultPolicy::SkipPayout, 0);
+        circle
+            .mark_defaults(&[test_pubkey(1), test_pubkey(2)], 1234569101)
+            .unwrap();
+        circle.apply(&due_payout(&circle)).unwrap();
+        fund_round(&mut circle);
+        circle.apply(&due_payout(&circle)).unwrap();
+
+        // Member 3 paid two rounds in and takes that back; the rest of the full
+        // pot is paid out to the others
+        fund_round(&mut circle);
+        assert_eq!(circle.full_pot().unwrap(), 300_000);
+        let payments = circle.apply(&due_payout(&circle)).unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                (test_pubkey(3), 200_000),
+                (test_pubkey(1), 50_000),
+                (test_pubkey(2), 50_000),
+            ]
+        );
+        assert!(circle.is_complete);
+        circle.validate().unwrap();
+
+        let received: Vec<Satoshis> = circle
+            .members
+            .iter()
+            .map(|m| m.payout_amount + m.auction_dividends)
+            .collect();
+        assert_eq!(received, vec![250_000, 350_000, 200_000]);
+        assert_eq!(received[2], CircleState::paid_in(&circle.members[2]));
+
+        // Odd sats are shared one each in joining order
+        let mut uneven = circle_with_missed_contribution(DefaultPolicy::SkipPayout, 0);
+        uneven.fee = Some(CircleFee {
+            rate: FeeRate::Flat(1),
+            recipient: test_pubkey(9),
+        });
+        uneven
+            .mark_defaults(&[test_pubkey(1), test_pubkey(2)], 1234569101)
+            .unwrap();
+        for _ in 0..2 {
+            uneven.apply(&due_payout(&uneven)).unwrap();
+            fund_round(&mut uneven);
+        }
+        let payments = uneven.apply(&due_payout(&uneven)).unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                (test_pubkey(3), 200_000),
+                (test_pubkey(9), 1),
+                (test_pubkey(1), 50_000),
+                (test_pubkey(2), 49_999),
+            ]
+        );
+        uneven.validate().unwrap();
+    }
+
+    #[test]
+    fn test_contract_marks_defaults() {
+        let app = test_app();
+        let prev = circle_with_missed_contribution(DefaultPolicy::Collateral, 150_000);
+
+        let mark = CircleAction::MarkDefaults {
+            approvals: vec![test_pubkey(1), test_pubkey(2)],
+            timestamp: 1234569101,
+        };
+        let mut marked = prev.clone();
+        assert!(marked.apply(&mark).unwrap().is_empty());
+        assert_eq!(marked.members[2].collateral, 50_000);
+        assert_eq!(marked.locked_value().unwrap(), prev.locked_value().unwrap());
+
+        // A majority of members who paid can mark defaults once the round has closed
+        let tx = transition_tx(&app, Some(&prev), &marked);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234569101),
+            &signed(&prev, &mark)
+        ));
+        assert!(!app_contract(&app, &tx, &at(1234569101), &unsigned(&mark)));
+
+        // So can the member being marked
+        let confessed = CircleAction::MarkDefaults {
+            approvals: vec![test_pubkey(3)],
+            timestamp: 1234569101,
+        };
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234569101),
+            &signed(&prev, &confessed)
+        ));
+
+        // Forfeited collateral cannot leave the circle
+        let mut drained = tx.clone();
+        drained.coin_outs.as_mut().unwrap()[0].amount -= 100_000;
+        assert!(!app_contract(
+            &app,
+            &drained,
+            &at(1234569101),
+            &signed(&prev, &mark)
+        ));
+
+        // Not before the round closes
+        let early = CircleAction::MarkDefaults {
+            approvals: vec![test_pubkey(1), test_pubkey(2)],
+            timestamp: 1234569050,
+        };
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234569050),
+            &signed(&prev, &early)
+        ));
+
+        // An outsider cannot forge a time past the cutoff as soon as the round
+        // opens to seize unpaid members' collateral
+        let forged = CircleAction::MarkDefaults {
+            approvals: vec![test_pubkey(9)],
+            timestamp: 1234569101,
+        };
+        assert!(prev.clone().apply(&forged).is_err());
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(1234569101),
+            &unsigned(&forged)
+        ));
+    }
+
+    #[test]
+    fn test_single_member_cannot_mark_defaults() {
+        let app = test_app();
+        let mut opened = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        opened.default_policy = DefaultPolicy::Collateral;
+        opened.collateral_per_member = 150_000;
+        for n in 1..=3 {
+            opened
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+        opened.start_circle(&test_pubkey(1), 1234568000).unwrap();
+
+        // As soon as the round opens, member 1 pays and stamps a time past the
+        // cutoff to mark everyone else defaulted and take their bonds
+        opened
+            .record_contribution(&test_pubkey(1), 100_000, 1234568001, [3u8; 32])
+            .unwrap();
+        let forged_at = opened.contribution_cutoff() + 1;
+        let mut seized = opened.clone();
+        seized
+            .mark_defaults(&[test_pubkey(2), test_pubkey(3)], forged_at)
+            .unwrap();
+        assert_eq!(seized.current_pool, 300_000);
+        let tx = transition_tx(&app, Some(&opened), &seized);
+
+        for approvals in [
+            vec![test_pubkey(1)],
+            vec![test_pubkey(1), test_pubkey(1)],
+            // A marked member does not count towards the quorum of those who paid
+            vec![test_pubkey(1), test_pubkey(2)],
+        ] {
+            let forged = CircleAction::MarkDefaults {
+                approvals,
+                timestamp: forged_at,
+            };
+            assert!(opened.clone().apply(&forged).is_err());
+            assert!(!app_contract(
+                &app,
+                &tx,
+                &at(forged_at),
+                &signed(&opened, &forged)
+            ));
+        }
+
+        // Only with every marked member's consent
+        let consented = CircleAction::MarkDefaults {
+            approvals: vec![test_pubkey(2), test_pubkey(3)],
+            timestamp: forged_at,
+        };
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(forged_at),
+            &signed(&opened, &consented)
+        ));
+    }
+
+    #[test]
+    fn test_collateral_deposits() {
+        let app = test_app();
+        let mut forming = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        forming.collateral_per_member = 50_000;
+        forming.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        assert_eq!(forming.members[0].collateral, 50_000);
+        assert_eq!(forming.locked_value().unwrap(), 50_000);
+
+        // Joining locks the bond in the circle UTXO
+        let join = CircleAction::Join {
+            pubkey: test_pubkey(2),
+            payout_round: 1,
+            joined_at: 1234567891,
+        };
+        let mut joined = forming.clone();
+        joined.apply(&join).unwrap();
+        assert_eq!(joined.locked_value().unwrap(), 100_000);
+        let tx = transition_tx(&app, Some(&forming), &joined);
+        let witness = signed(&forming, &join);
+        assert!(app_contract(&app, &tx, &at(1234567891), &witness));
+
+        let mut unbonded = tx.clone();
+        unbonded.coin_outs.as_mut().unwrap()[0] = circle_coin(&forming);
+        assert!(!app_contract(&app, &unbonded, &at(1234567891), &witness));
+
+        // Bonds cannot be released early or changed
+        let mut released = joined.clone();
+        released.members[1].collateral = 0;
+        assert!(released.validate().is_err());
+        let mut cheaper = joined.clone();
+        cheaper.collateral_per_member = 0;
+        cheaper.prev_state_hash = joined.state_hash();
+        assert!(joined.validate_transition(&cheaper).is_err());
+
+        // Run both rounds; the bonds stay locked through the last payout
+        let mut circle = joined.clone();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        fund_round(&mut circle);
+        let payments = circle.apply(&due_payout(&circle)).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(1), 200_000)]);
+
+        fund_round(&mut circle);
+        let due = circle.round_deadline();
+        let payout = due_payout(&circle);
+        let funded = circle.clone();
+        let payments = circle.apply(&payout).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(2), 200_000)]);
+        assert!(circle.is_complete);
+        assert_eq!(circle.locked_value().unwrap(), 100_000);
+        circle.validate().unwrap();
+
+        let mut tx = transition_tx(&app, Some(&funded), &circle);
+        pay(&mut tx, &test_pubkey(2), 200_000);
+        assert!(app_contract(&app, &tx, &at(due), &signed(&funded, &payout)));
+
+        // Bonds are returned when the circle is settled
+        let settle = CircleAction::Settle {
+            pubkey: test_pubkey(1),
+            timestamp: due,
+        };
+        let completed = circle.clone();
+        let payments = circle.apply(&settle).unwrap();
+        assert_eq!(
+            payments,
+            vec![(test_pubkey(1), 50_000), (test_pubkey(2), 50_000)]
+        );
+        assert_eq!(circle.locked_value().unwrap(), 0);
+        circle.validate().unwrap();
+
+        let mut tx = transition_tx(&app, Some(&completed), &circle);
+        for (recipient, amount) in &payments {
+            pay(&mut tx, recipient, *amount);
+        }
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(due),
+            &signed(&completed, &settle)
+        ));
+
+        // A bond kept by the circle instead of being returned
+        let mut kept = tx.clone();
+        kept.outs.pop();
+        kept.coin_outs.as_mut().unwrap().pop();
+        assert!(!app_contract(
+            &app,
+            &kept,
+            &at(due),
+            &signed(&completed, &settle)
+        ));
+    }
+
+    #[test]
+    fn test_remove_member() {
+        let app = test_app();
+        let mut forming = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        forming.collateral_per_member = 50_000;
+        for n in 1..=3 {
+            forming
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+
+        // Member 2 leaves; member 3 moves up to round 1 and the bond is refunded
+        let leave = CircleAction::RemoveMember {
+            pubkey: test_pubkey(2),
+            timestamp: 1234567900,
+        };
+        let mut left = forming.clone();
+        let payments = left.apply(&leave).unwrap();
+        assert_eq!(payments, vec![(test_pubkey(2), 50_000)]);
+        assert_eq!(left.members.len(), 2);
+        assert_eq!(left.members[1].pubkey, test_pubkey(3));
+        assert_eq!(left.members[1].payout_round, 1);
+        left.validate().unwrap();
+
+        let mut tx = transition_tx(&app, Some(&forming), &left);
+        pay(&mut tx, &test_pubkey(2), 50_000);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234567900),
+            &signed(&forming, &leave)
+        ));
+        assert!(!app_contract(&app, &tx, &at(1234567900), &unsigned(&leave)));
+
+        // The creator cannot leave, and nobody can once the circle started
+        assert!(forming.clone().remove_member(&test_pubkey(1)).is_err());
+        let mut started = forming.clone();
+        started.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        assert!(started.remove_member(&test_pubkey(2)).is_err());
+    }
+
+    #[test]
+    fn test_transfer_seat() {
+        let app = test_app();
+        let scroll = scroll_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        for n in 1..=3 {
+            circle
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+        assert!(circle
+            .clone()
+            .transfer_seat(&test_pubkey(2), test_pubkey(4))
+            .is_err());
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        fund_round(&mut circle);
+
+        // The seat keeps its history and payout round under the new key
+        let transfer = CircleAction::TransferSeat {
+            from: test_pubkey(2),
+            to: test_pubkey(4),
+            timestamp: 1234568100,
+        };
+        let mut moved = circle.clone();
+        moved.apply(&transfer).unwrap();
+        assert_eq!(moved.members[1].pubkey, test_pubkey(4));
+        assert_eq!(moved.members[1].payout_round, 1);
+        assert_eq!(moved.members[1].contribution_history.len(), 1);
+        moved.validate().unwrap();
+        assert!(moved
+            .clone()
+            .transfer_seat(&test_pubkey(4), test_pubkey(3))
+            .is_err());
+
+        // Both the old and the new key must sign
+        let tx = transition_tx(&app, Some(&circle), &moved);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234568100),
+            &signed(&circle, &transfer)
+        ));
+        let hash = circle.signing_hash(&transfer);
+        let only_seller: Signature = test_key(2).sign_prehash(&hash).unwrap();
+        let only_seller = Data::from(&CircleWitness {
+            action: transfer.clone(),
+            signatures: vec![only_seller.to_bytes().to_vec()],
+        });
+        assert!(!app_contract(&app, &tx, &at(1234568100), &only_seller));
+
+        // The seat's past contributions do not mint new scrolls
+        let mut minted = tx.clone();
+        let mut scroll_out = Charms::new();
+        scroll_out.insert(scroll.clone(), scroll_data());
+        minted.outs.push(scroll_out);
+        minted
+            .coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(546, test_pubkey(4).p2wpkh_script()));
+        assert!(!app_contract(
+            &scroll,
+            &minted,
+            &Data::empty(),
+            &Data::empty()
+        ));
+
+        // The new key takes over the seat's obligations and payout rights
+        moved.execute_payout(moved.round_deadline(), None).unwrap();
+        fund_round(&mut moved);
+        let (recipient, _) = moved.execute_payout(moved.round_deadline(), None).unwrap();
+        assert_eq!(recipient, test_pubkey(4));
+    }
+
+    #[test]
+    fn test_multi_seat_membership() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.add_member(test_pubkey(1), 2, 1234567892).unwrap();
+        assert_eq!(circle.members.len(), 3);
+        assert_eq!(circle.total_rounds, 3);
+        circle.validate().unwrap();
+
+        // Giving up a share removes the member's latest seat, never the creator's first
+        let mut fewer = circle.clone();
+        fewer.remove_member(&test_pubkey(1)).unwrap();
+        assert_eq!(fewer.members.len(), 2);
+        assert_eq!(fewer.members[0].pubkey, test_pubkey(1));
+        assert!(fewer.remove_member(&test_pubkey(1)).is_err());
+
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+
+        // Each contribution pays for the member's next seat still owing this round
+        circle
+            .record_contribution(&test_pubkey(1), 100_000, 1234568100, [3u8; 32])
+            .unwrap();
+        let contribute = CircleAction::Contribute {
+            pubkey: test_pubkey(1),
+            amount: 100_000,
+            timestamp: 1234568200,
+            txid: [3u8; 32],
+        };
+        let mut paid_twice = circle.clone();
+        paid_twice.apply(&contribute).unwrap();
+        assert_eq!(paid_twice.members[0].contribution_history.len(), 1);
+        assert_eq!(paid_twice.members[2].contribution_history.len(), 1);
+        assert_eq!(paid_twice.current_pool, 200_000);
+        assert!(!paid_twice.is_round_fully_funded());
+        paid_twice.validate().unwrap();
+        assert!(paid_twice
+            .clone()
+            .record_contribution(&test_pubkey(1), 100_000, 1234568300, [3u8; 32])
+            .is_err());
+
+        let tx = transition_tx(&app, Some(&circle), &paid_twice);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234568200),
+            &signed(&circle, &contribute)
+        ));
+
+        // Every seat is paid its own pot
+        let mut circle = paid_twice;
+        circle
+            .record_contribution(&test_pubkey(2), 100_000, 1234568300, [3u8; 32])
+            .unwrap();
+        assert!(circle.is_round_fully_funded());
+        let mut recipients = Vec::new();
+        for _ in 0..3 {
+            if !circle.is_round_fully_funded() {
+                fund_round(&mut circle);
+            }
+            recipients.push(
+                circle
+                    .execute_payout(circle.round_deadline(), None)
+                    .unwrap(),
+            );
+            circle.validate().unwrap();
+        }
+        assert_eq!(
+            recipients,
+            vec![
+                (test_pubkey(1), 300_000),
+                (test_pubkey(2), 300_000),
+                (test_pubkey(1), 300_000)
+            ]
+        );
+        assert!(circle.is_complete);
+    }
 
-        // Check if circle is complete
-        if self.current_round >= self.total_rounds {
-            self.is_complete = true;
+    #[test]
+    fn test_auction_bid_wins_next_unpaid_seat() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.payout_policy = PayoutPolicy::Auction;
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 0, 1234567891).unwrap();
+        circle.add_member(test_pubkey(2), 0, 1234567892).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+
+        // Member 2 wins twice, once with each seat
+        for round in 0..2 {
+            fund_round(&mut circle);
+            circle
+                .place_bid(&test_pubkey(2), 30_000, circle.round_started_at)
+                .unwrap();
+            circle.validate().unwrap();
+            let (recipient, amount) = circle
+                .execute_payout(circle.round_deadline(), None)
+                .unwrap();
+            assert_eq!((recipient, amount), (test_pubkey(2), 270_000));
+            assert_eq!(circle.members[1 + round].payout_round, round as u32);
+            circle.validate().unwrap();
         }
 
-        Ok((recipient, payout_amount))
+        // With both seats paid, member 2 can no longer bid
+        fund_round(&mut circle);
+        assert!(circle
+            .place_bid(&test_pubkey(2), 30_000, circle.round_started_at)
+            .is_err());
     }
 
-    /// Validate state transition is allowed
-    pub fn validate_transition(&self, next_state: &CircleState) -> Result<(), String> {
-        // Must be same circle
-        if self.circle_id != next_state.circle_id {
-            return Err("Circle ID mismatch".to_string());
+    #[test]
+    fn test_payout_fees() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_001, 1_000, 1234567890, 2, 10);
+        circle.fee = Some(CircleFee {
+            rate: FeeRate::BasisPoints(250),
+            recipient: test_pubkey(9),
+        });
+        for n in 1..=3 {
+            circle
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
         }
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        fund_round(&mut circle);
 
-        // Cannot modify members after start
-        if self.current_round > 0 && self.members.len() != next_state.members.len() {
-            return Err("Cannot change member count after start".to_string());
-        }
+        // 2.5% of 300,003 is 7,500.075 sats: the fee rounds down
+        let payout = due_payout(&circle);
+        let mut paid = circle.clone();
+        let payments = paid.apply(&payout).unwrap();
+        assert_eq!(
+            payments,
+            vec![(test_pubkey(1), 292_503), (test_pubkey(9), 7_500)]
+        );
+        assert_eq!(paid.members[0].fee_paid, 7_500);
+        assert_eq!(paid.fees_collected, 7_500);
+        paid.validate().unwrap();
 
-        // Round can only increment by 0 or 1
-        if next_state.current_round > self.current_round + 1 {
-            return Err("Invalid round progression".to_string());
-        }
+        // The contract requires the fee output
+        let due = circle.round_deadline();
+        let mut tx = transition_tx(&app, Some(&circle), &paid);
+        pay(&mut tx, &test_pubkey(1), 292_503);
+        let mut unpaid_fee = tx.clone();
+        pay(&mut tx, &test_pubkey(9), 7_500);
+        assert!(app_contract(&app, &tx, &at(due), &signed(&circle, &payout)));
+        unpaid_fee.coin_outs.as_mut().unwrap()[1].amount = 300_003;
+        assert!(!app_contract(
+            &app,
+            &unpaid_fee,
+            &at(due),
+            &signed(&circle, &payout)
+        ));
 
-        // Pool can only increase or reset to 0
-        if next_state.current_round == self.current_round {
-            // Same round: pool should increase
-            if next_state.current_pool < self.current_pool {
-                return Err("Pool cannot decrease within round".to_string());
-            }
-        } else {
-            // New round: pool should reset
-            if next_state.current_pool != 0 {
-                return Err("Pool must reset on new round".to_string());
-            }
-        }
+        // Fee accounting must add up, and the fee is fixed at creation
+        let mut skimmed = paid.clone();
+        skimmed.fees_collected += 1;
+        assert!(skimmed.validate().is_err());
+        let mut raised = paid.clone();
+        raised.fee = Some(CircleFee {
+            rate: FeeRate::BasisPoints(500),
+            recipient: test_pubkey(9),
+        });
+        raised.prev_state_hash = circle.state_hash();
+        assert!(circle.validate_transition(&raised).is_err());
 
-        Ok(())
+        // A flat fee never takes more than the pot, and rates above 100% are invalid
+        let mut flat = circle.clone();
+        flat.fee = Some(CircleFee {
+            rate: FeeRate::Flat(1_000_000),
+            recipient: test_pubkey(9),
+        });
+        assert_eq!(flat.fee_for(300_003), Ok(300_003));
+        flat.fee = Some(CircleFee {
+            rate: FeeRate::BasisPoints(10_001),
+            recipient: test_pubkey(9),
+        });
+        assert!(flat.validate().is_err());
     }
 
-    /// Validate the entire state for consistency
-    pub fn validate(&self) -> Result<(), String> {
-        // Check basic constraints
-        // For a valid circle, we must have members (even if current_round is 0)
-        // The only exception is during initial creation before first member is added,
-        // but app_contract handles that case separately
-        if self.members.is_empty() {
-            return Err("Circle has no members".to_string());
+    #[test]
+    fn test_cancel_circle() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.collateral_per_member = 10_000;
+        circle.cancel_quorum = 2;
+        for n in 1..=3 {
+            circle
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        fund_round(&mut circle);
+        circle
+            .execute_payout(circle.round_deadline(), None)
+            .unwrap();
+        for n in 1..=2 {
+            circle
+                .record_contribution(&test_pubkey(n), 100_000, 1234569100, [3u8; 32])
+                .unwrap();
         }
 
-        if self.total_rounds != self.members.len() as u32 {
-            return Err(format!(
-                "Total rounds ({}) must equal number of members ({})",
-                self.total_rounds,
-                self.members.len()
-            ));
+        // Member 1 was paid more than they put in; members 2 and 3 are owed 200,000
+        // and 100,000, so the 200,000 pool is split 2:1 with the odd sat to member 2
+        let cancel = CircleAction::Cancel {
+            approvals: vec![test_pubkey(2), test_pubkey(3)],
+            timestamp: 1234569200,
+        };
+        let mut cancelled = circle.clone();
+        let payments = cancelled.apply(&cancel).unwrap();
+        assert_eq!(
+            payments,
+            vec![
+                (test_pubkey(1), 10_000),
+                (test_pubkey(2), 143_334),
+                (test_pubkey(3), 76_666)
+            ]
+        );
+        assert_eq!(cancelled.phase, CirclePhase::Cancelled);
+        assert_eq!(cancelled.locked_value().unwrap(), 0);
+        cancelled.validate().unwrap();
+
+        let mut tx = transition_tx(&app, Some(&circle), &cancelled);
+        for (recipient, amount) in &payments {
+            pay(&mut tx, recipient, *amount);
         }
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234569200),
+            &signed(&circle, &cancel)
+        ));
 
-        if self.current_round > self.total_rounds {
-            return Err(format!(
-                "Current round ({}) exceeds total rounds ({})",
-                self.current_round, self.total_rounds
-            ));
+        // Each member's refund and collateral are paid by a single output
+        let mut split = transition_tx(&app, Some(&circle), &cancelled);
+        for (n, amount) in [
+            (1, 10_000),
+            (2, 133_334),
+            (2, 10_000),
+            (3, 66_666),
+            (3, 10_000),
+        ] {
+            pay(&mut split, &test_pubkey(n), amount);
         }
+        assert!(!app_contract(
+            &app,
+            &split,
+            &at(1234569200),
+            &signed(&circle, &cancel)
+        ));
 
-        if self.current_payout_index >= self.members.len() {
-            return Err(format!(
-                "Invalid payout index ({}), must be < {}",
-                self.current_payout_index,
-                self.members.len()
-            ));
+        // Refunds must follow what members are owed
+        let mut uneven = tx.clone();
+        uneven.coin_outs.as_mut().unwrap()[2].amount = 110_000;
+        uneven.coin_outs.as_mut().unwrap()[3].amount = 110_000;
+        assert!(!app_contract(
+            &app,
+            &uneven,
+            &at(1234569200),
+            &signed(&circle, &cancel)
+        ));
+        let mut skewed = cancelled.clone();
+        skewed.members[1].refund -= 1;
+        skewed.members[2].refund += 1;
+        assert!(skewed.validate().is_err());
+
+        // One approval is short of the quorum, however late the time it attests
+        assert!(circle.clone().cancel(&[test_pubkey(2)]).is_err());
+        assert!(circle
+            .clone()
+            .cancel(&[test_pubkey(2), test_pubkey(2)])
+            .is_err());
+        assert!(circle
+            .clone()
+            .cancel(&[test_pubkey(9), test_pubkey(2)])
+            .is_err());
+        assert!(circle.clone().cancel(&[]).is_err());
+        let stalled = 4_000_000_000;
+        let lone = CircleAction::Cancel {
+            approvals: vec![test_pubkey(2)],
+            timestamp: stalled,
+        };
+        assert!(circle.clone().apply(&lone).is_err());
+        let mut tx = transition_tx(&app, Some(&circle), &cancelled);
+        for (recipient, amount) in &payments {
+            pay(&mut tx, recipient, *amount);
         }
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(stalled),
+            &signed(&circle, &lone)
+        ));
 
-        // Validate each member
-        for member in &self.members {
-            // Check payout round is valid
-            if member.payout_round >= self.total_rounds {
-                return Err("Member has invalid payout round".to_string());
-            }
+        // Nothing happens after cancellation
+        assert!(cancelled.execute_payout(1234570000, None).is_err());
+        assert!(cancelled.cancel(&[test_pubkey(1)]).is_err());
+    }
 
-            // If payout received, must be in past rounds
-            if member.has_received_payout && member.payout_round >= self.current_round {
-                return Err("Member marked as paid but round hasn't occurred".to_string());
-            }
+    #[test]
+    fn test_settlement() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.fee = Some(CircleFee {
+            rate: FeeRate::Flat(1_000),
+            recipient: test_pubkey(9),
+        });
+        circle.collateral_per_member = 5_000;
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.add_member(test_pubkey(1), 2, 1234567892).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        assert!(circle.clone().settle(1234568000).is_err());
+        for _ in 0..3 {
+            fund_round(&mut circle);
+            circle
+                .execute_payout(circle.round_deadline(), None)
+                .unwrap();
+        }
+        assert_eq!(circle.phase, CirclePhase::Settling);
 
-            // Validate contribution history
-            let mut rounds_seen = HashMap::new();
-            for contrib in &member.contribution_history {
-                if contrib.round >= self.total_rounds {
-                    return Err("Invalid contribution round".to_string());
-                }
+        // The summary adds up each member's seats and accounts for every sat
+        let settled_at = circle.round_started_at;
+        let settle = CircleAction::Settle {
+            pubkey: test_pubkey(1),
+            timestamp: settled_at,
+        };
+        let mut settled = circle.clone();
+        settled.apply(&settle).unwrap();
+        settled.validate().unwrap();
+        let settlement = settled.settlement.clone().unwrap();
+        assert_eq!(settlement.total_paid_in, 900_000);
+        assert_eq!(settlement.total_paid_out, 897_000);
+        assert_eq!(settlement.fees_paid, 3_000);
+        assert_eq!(settlement.collateral_returned, 15_000);
+        assert_eq!(settlement.positions.len(), 2);
+        assert_eq!(settlement.positions[0].pubkey, test_pubkey(1));
+        assert_eq!(settlement.positions[0].net(), -2_000);
+        assert_eq!(settlement.positions[1].net(), -1_000);
 
-                if contrib.amount != self.contribution_per_round {
-                    return Err("Invalid contribution amount".to_string());
-                }
+        let mut tampered = settled.clone();
+        tampered.settlement.as_mut().unwrap().positions[1].paid_out += 1;
+        assert!(tampered.validate().is_err());
 
-                // Check for duplicates
-                if rounds_seen.contains_key(&contrib.round) {
-                    return Err("Duplicate contribution for round".to_string());
-                }
-                rounds_seen.insert(contrib.round, true);
-            }
+        // Once settled, the circle state can no longer change
+        let mut edited = settled.clone();
+        edited.members[0].payout_amount = 0;
+        edited.prev_state_hash = settled.state_hash();
+        assert!(settled.validate_transition(&edited).is_err());
+        assert!(settled.clone().settle(settled_at).is_err());
+
+        let mut tx = transition_tx(&app, Some(&settled), &settled);
+        tx.coin_ins = Some(vec![coin_out(0, vec![0x51])]);
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(settled_at),
+            &signed(&settled, &settle)
+        ));
+    }
+
+    #[test]
+    fn test_renew_circle() {
+        let app = test_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.collateral_per_member = 5_000;
+        for n in 1..=3 {
+            circle
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890 + n as u64)
+                .unwrap();
         }
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        for _ in 0..3 {
+            fund_round(&mut circle);
+            circle
+                .execute_payout(circle.round_deadline(), None)
+                .unwrap();
+        }
+        let renewed_at = circle.round_started_at + 10;
+        assert!(circle
+            .renew(
+                &funding_outpoint(),
+                renewed_at,
+                &[test_pubkey(1)],
+                RenewalOrder::Keep
+            )
+            .is_err());
+        circle.settle(circle.round_started_at).unwrap();
 
-        // Validate current pool
-        let expected_pool: Satoshis = self
+        // Members 1 and 3 run it again; member 1, paid first last time, now goes last
+        let renew = CircleAction::Renew {
+            members: vec![test_pubkey(3), test_pubkey(1)],
+            order: RenewalOrder::Rotate,
+            timestamp: renewed_at,
+        };
+        let circle_utxo = UtxoId(TxId([9u8; 32]), 0);
+        let renewed = circle
+            .renew(
+                &circle_utxo,
+                renewed_at,
+                &[test_pubkey(3), test_pubkey(1)],
+                RenewalOrder::Rotate,
+            )
+            .unwrap();
+        assert_eq!(renewed.phase, CirclePhase::Forming);
+        assert_eq!(
+            renewed.circle_id,
+            CircleState::derive_circle_id(&circle_utxo)
+        );
+        assert_eq!(
+            renewed.predecessor,
+            Some(Predecessor {
+                circle_id: circle.circle_id,
+                final_state_hash: circle.state_hash(),
+            })
+        );
+        let schedule: Vec<(PubKey, u32)> = renewed
             .members
             .iter()
-            .filter_map(|m| {
-                m.contribution_history
-                    .iter()
-                    .find(|c| c.round == self.current_round)
-                    .map(|c| c.amount)
-            })
-            .sum();
+            .map(|m| (m.pubkey.clone(), m.payout_round))
+            .collect();
+        assert_eq!(schedule, vec![(test_pubkey(1), 1), (test_pubkey(3), 0)]);
+        assert!(renewed.members.iter().all(|m| m.collateral == 5_000
+            && m.contribution_history.is_empty()
+            && !m.has_received_payout));
+        renewed.validate().unwrap();
 
-        if self.current_pool != expected_pool {
-            return Err(format!(
-                "Current pool mismatch. Expected: {}, Got: {}",
-                expected_pool, self.current_pool
-            ));
-        }
+        // The renewal spends the settled circle, with every renewing member's
+        // signature and their collateral locked again
+        let tx = transition_tx(&app, Some(&circle), &renewed);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(renewed_at),
+            &signed(&circle, &renew)
+        ));
+        let hash = circle.signing_hash(&renew);
+        let one_signature: Signature = test_key(3).sign_prehash(&hash).unwrap();
+        let one_signature = Data::from(&CircleWitness {
+            action: renew.clone(),
+            signatures: vec![one_signature.to_bytes().to_vec(); 2],
+        });
+        assert!(!app_contract(&app, &tx, &at(renewed_at), &one_signature));
+        let mut unfunded = tx.clone();
+        unfunded.coin_outs.as_mut().unwrap()[0].amount -= 10_000;
+        assert!(!app_contract(
+            &app,
+            &unfunded,
+            &at(renewed_at),
+            &signed(&circle, &renew)
+        ));
 
-        Ok(())
-    }
-}
+        // Reshuffles are seeded from the public input; seniority circles keep their order
+        let reshuffle = RenewalOrder::Reshuffle { seed: [5u8; 32] };
+        let reshuffled = circle
+            .renew(&circle_utxo, renewed_at, &[test_pubkey(1)], reshuffle)
+            .unwrap();
+        let reshuffle = CircleAction::Renew {
+            members: vec![test_pubkey(1)],
+            order: reshuffle,
+            timestamp: renewed_at,
+        };
+        let tx = transition_tx(&app, Some(&circle), &reshuffled);
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(renewed_at),
+            &signed(&circle, &reshuffle)
+        ));
+        let seeded = Data::from(&CircleInputs {
+            timestamp: renewed_at,
+            seed: Some([5u8; 32]),
+        });
+        assert!(app_contract(
+            &app,
+            &tx,
+            &seeded,
+            &signed(&circle, &reshuffle)
+        ));
 
-/// Internal implementation using Result for better error handling
-/// Following the BRO token pattern
-fn app_contract_impl(app: &App, tx: &Transaction, _x: &Data, _w: &Data) -> Result<()> {
-    // HACKATHON SIMPLIFIED VERSION:
-    // For the hackathon, we're using simplified validation that just checks
-    // that charm data exists in outputs. Full CBOR deserialization validation
-    // will be re-enabled after debugging WASM runtime issues.
+        let mut seniority = circle.clone();
+        seniority.payout_policy = PayoutPolicy::Seniority;
+        assert!(seniority
+            .renew(
+                &circle_utxo,
+                renewed_at,
+                &[test_pubkey(1)],
+                RenewalOrder::Rotate
+            )
+            .is_err());
+    }
 
-    // Step 1: Extract new state from transaction outputs
-    // Find the output containing our app's data
-    let new_state_data = tx
-        .outs
-        .iter()
-        .find_map(|out| out.get(app))
-        .ok_or_else(|| anyhow::anyhow!("No charm data found for app in outputs"))?;
+    #[test]
+    fn test_installments() {
+        let app = test_app();
+        let scroll = scroll_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
 
-    // Step 2: Just verify data exists (simplified for hackathon)
-    ensure!(!new_state_data.is_empty(), "Charm data cannot be empty");
+        // Member 1 pays in two installments; only the total counts
+        circle
+            .record_contribution(&test_pubkey(1), 40_000, 1234568100, [3u8; 32])
+            .unwrap();
+        assert!(!circle.is_round_fully_funded());
+        circle.validate().unwrap();
+        assert!(circle
+            .clone()
+            .record_contribution(&test_pubkey(1), 60_001, 1234568200, [3u8; 32])
+            .is_err());
 
-    // Success - data exists and is non-empty
-    Ok(())
-}
+        let installment = CircleAction::Contribute {
+            pubkey: test_pubkey(1),
+            amount: 60_000,
+            timestamp: 1234568200,
+            txid: [3u8; 32],
+        };
+        let mut paid = circle.clone();
+        paid.apply(&installment).unwrap();
+        assert_eq!(paid.members[0].contribution_history.len(), 2);
+        assert_eq!(paid.current_pool, 100_000);
+        paid.validate().unwrap();
+        assert!(paid
+            .clone()
+            .record_contribution(&test_pubkey(1), 1, 1234568300, [3u8; 32])
+            .is_err());
 
-/// Charms covenant contract function for ROSCA
-/// This function validates state transitions for the ROSCA circle
-/// Wrapper that converts Result to bool (BRO token pattern)
-pub fn app_contract(app: &App, tx: &Transaction, x: &Data, w: &Data) -> bool {
-    app_contract_impl(app, tx, x, w)
-        .map_err(|e| {
-            // In WASM, this won't print, but helps with debugging in tests
-            #[cfg(not(target_arch = "wasm32"))]
-            eprintln!("Contract validation failed: {:?}", e);
-            e
-        })
-        .is_ok()
-}
+        // Each installment mints its own scroll and moves real sats
+        let mut tx = transition_tx(&app, Some(&circle), &paid);
+        let mut scroll_out = Charms::new();
+        scroll_out.insert(scroll.clone(), scroll_data());
+        tx.outs.push(scroll_out);
+        tx.coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(546, test_pubkey(1).p2wpkh_script()));
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234568200),
+            &signed(&circle, &installment)
+        ));
+        assert!(app_contract(&scroll, &tx, &Data::empty(), &Data::empty()));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // Overpaying is rejected by validate as well
+        let mut overpaid = paid.clone();
+        overpaid.members[0].contribution_history[1].amount = 60_001;
+        overpaid.current_pool += 1;
+        assert!(overpaid.validate().is_err());
 
-    fn test_pubkey(n: u8) -> PubKey {
-        let mut key = vec![0u8; 33];
-        key[0] = 0x02; // Compressed pubkey prefix
-        key[1] = n;
-        PubKey(key)
+        // A member who paid part of the round defaults on the rest
+        let mut circle = paid;
+        circle
+            .record_contribution(&test_pubkey(2), 30_000, 1234568300, [3u8; 32])
+            .unwrap();
+        // The marked member consents to their own default
+        circle
+            .mark_defaults(&[test_pubkey(2)], circle.contribution_cutoff() + 1)
+            .unwrap();
+        assert!(!circle.members[0].has_defaulted());
+        assert_eq!(circle.members[1].defaults[0].shortfall, 70_000);
+        circle.validate().unwrap();
+        let (recipient, amount) = circle
+            .execute_payout(circle.contribution_cutoff() + 1, None)
+            .unwrap();
+        assert_eq!((recipient, amount), (test_pubkey(1), 130_000));
+        circle.validate().unwrap();
     }
 
     #[test]
-    fn test_create_circle() {
-        let circle_id = [1u8; 32];
-        let circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890);
+    fn test_prepay_future_rounds() {
+        let app = test_app();
+        let scroll = scroll_app();
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.add_member(test_pubkey(3), 2, 1234567892).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
 
-        assert_eq!(circle.current_round, 0);
-        assert_eq!(circle.members.len(), 0);
-        assert!(!circle.is_complete);
+        // Nothing can be prepaid beyond the last round
+        assert!(circle
+            .clone()
+            .prepay(&test_pubkey(1), 300_001, 1234568100, [4u8; 32])
+            .is_err());
+        assert!(circle
+            .clone()
+            .prepay(&test_pubkey(4), 100_000, 1234568100, [4u8; 32])
+            .is_err());
+
+        // This round is paid at once, the rest is held in escrow
+        let prepay = CircleAction::Prepay {
+            pubkey: test_pubkey(1),
+            amount: 250_000,
+            timestamp: 1234568100,
+            txid: [4u8; 32],
+        };
+        let mut prepaid = circle.clone();
+        assert!(prepaid.apply(&prepay).unwrap().is_empty());
+        assert_eq!(prepaid.current_pool, 100_000);
+        assert_eq!(prepaid.members[0].prepaid, 150_000);
+        assert_eq!(prepaid.locked_value().unwrap(), 250_000);
+        prepaid.validate().unwrap();
+
+        // Only this round's contribution mints a scroll; all sats enter the circle
+        let mut tx = transition_tx(&app, Some(&circle), &prepaid);
+        let mut scroll_out = Charms::new();
+        scroll_out.insert(scroll.clone(), scroll_data());
+        tx.outs.push(scroll_out);
+        tx.coin_outs
+            .as_mut()
+            .unwrap()
+            .push(coin_out(546, test_pubkey(1).p2wpkh_script()));
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(1234568100),
+            &signed(&circle, &prepay)
+        ));
+        assert!(app_contract(&scroll, &tx, &Data::empty(), &Data::empty()));
+
+        // The next round opens with the prepaid contribution already in the pool
+        let mut circle = prepaid;
+        for n in [2, 3] {
+            circle
+                .record_contribution(&test_pubkey(n), 100_000, 1234568200, [3u8; 32])
+                .unwrap();
+        }
+        let payout = due_payout(&circle);
+        let mut paid = circle.clone();
+        let payments = paid.apply(&payout).unwrap();
+        assert_eq!(payments, [(test_pubkey(1), 300_000)]);
+        assert_eq!(paid.current_pool, 100_000);
+        assert_eq!(paid.members[0].prepaid, 50_000);
+        assert!(paid.members[0].contribution_history[1].prepaid);
+        paid.validate().unwrap();
+
+        let mut tx = transition_tx(&app, Some(&circle), &paid);
+        pay(&mut tx, &test_pubkey(1), 300_000);
+        assert!(app_contract(
+            &app,
+            &tx,
+            &at(circle.round_deadline()),
+            &signed(&circle, &payout)
+        ));
+
+        // Credits cannot be dropped from the new round's pool
+        let mut dropped = paid.clone();
+        dropped.members[0].contribution_history.pop();
+        dropped.members[0].prepaid += 100_000;
+        dropped.current_pool = 0;
+        assert!(circle.validate_transition(&dropped).is_err());
+
+        // A partial credit is topped up with an installment
+        let mut circle = paid;
+        for n in [2, 3] {
+            circle
+                .record_contribution(&test_pubkey(n), 100_000, circle.round_started_at, [3u8; 32])
+                .unwrap();
+        }
+        circle
+            .execute_payout(circle.round_deadline(), None)
+            .unwrap();
+        assert_eq!(circle.members[0].prepaid, 0);
+        assert_eq!(circle.current_pool, 50_000);
+        circle
+            .record_contribution(&test_pubkey(1), 50_000, circle.round_started_at, [3u8; 32])
+            .unwrap();
+        assert!(circle
+            .clone()
+            .prepay(&test_pubkey(2), 100_001, circle.round_started_at, [4u8; 32])
+            .is_err());
+        circle.validate().unwrap();
+
+        // Escrow over the remaining rounds is rejected
+        let mut hoarded = circle.clone();
+        hoarded.members[1].prepaid = 1;
+        assert!(hoarded.validate().is_err());
     }
 
     #[test]
-    fn test_add_members() {
-        let circle_id = [1u8; 32];
-        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890);
-
+    fn test_cancel_refunds_prepaid_escrow() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
         circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
         circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
-        circle.add_member(test_pubkey(3), 2, 1234567892).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        circle
+            .prepay(&test_pubkey(2), 200_000, 1234568100, [4u8; 32])
+            .unwrap();
 
-        assert_eq!(circle.members.len(), 3);
-        assert_eq!(circle.total_rounds, 3);
+        let cancel = CircleAction::Cancel {
+            approvals: vec![test_pubkey(1), test_pubkey(2)],
+            timestamp: 1234568200,
+        };
+        let payments = circle.apply(&cancel).unwrap();
+        assert_eq!(payments, [(test_pubkey(2), 200_000)]);
+        assert_eq!(circle.members[1].prepaid, 0);
+        assert_eq!(circle.locked_value().unwrap(), 0);
         circle.validate().unwrap();
     }
 
     #[test]
-    fn test_validate_new_circle_with_one_member() {
-        // This test simulates what serialize_state creates: a new circle with one member
-        let circle_id = [1u8; 32];
-        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890);
-
-        // Add creator as first member (payout_round 0)
+    fn test_rejects_zero_contribution() {
+        let mut circle = CircleState::new([1u8; 32], 0, 2_592_000, 1234567890, 2, 10);
+        circle.payout_policy = PayoutPolicy::Auction;
         circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        assert!(circle.validate_member_limits().is_err());
+        assert!(circle.validate().is_err());
 
-        // Verify state
-        assert_eq!(circle.members.len(), 1);
-        assert_eq!(circle.total_rounds, 1);
-        assert_eq!(circle.current_round, 0);
-        assert_eq!(circle.current_pool, 0);
-        assert_eq!(circle.current_payout_index, 0);
-        assert_eq!(circle.members[0].payout_round, 0);
+        // Bidding on an empty pot fails cleanly
+        circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        assert!(circle.place_bid(&test_pubkey(2), 1, 1234567900).is_err());
 
-        // This should pass validation
-        circle
-            .validate()
-            .expect("New circle with one member should validate");
+        // Oversized terms are rejected rather than overflowing
+        let mut huge = CircleState::new([1u8; 32], u64::MAX, 2_592_000, 1234567890, 2, 10);
+        huge.add_member(test_pubkey(1), 0, 1234567890).unwrap();
+        huge.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        assert!(huge.validate().is_err());
     }
 
     #[test]
-    fn test_contribution_and_payout() {
-        let circle_id = [1u8; 32];
-        let mut circle = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890);
-
-        // Add members
+    fn test_locked_value_overflow() {
+        let mut circle = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 2, 10);
         circle.add_member(test_pubkey(1), 0, 1234567890).unwrap();
         circle.add_member(test_pubkey(2), 1, 1234567891).unwrap();
+        circle.start_circle(&test_pubkey(1), 1234567895).unwrap();
+        circle.members[1].collateral = u64::MAX - 50_000;
 
-        // Record contributions
-        let txid = [0u8; 32];
-        circle
-            .record_contribution(&test_pubkey(1), 100_000, 1234567900, txid)
-            .unwrap();
-        circle
-            .record_contribution(&test_pubkey(2), 100_000, 1234567901, txid)
-            .unwrap();
+        // Deposits that would overflow what the circle UTXO locks are rejected
+        let before = circle.clone();
+        assert!(circle
+            .record_contribution(&test_pubkey(1), 100_000, 1234567900, [1u8; 32])
+            .is_err());
+        assert!(circle
+            .prepay(&test_pubkey(1), 300_000, 1234567900, [1u8; 32])
+            .is_err());
+        assert_eq!(circle, before);
 
-        assert!(circle.is_round_fully_funded());
-        assert_eq!(circle.current_pool, 200_000);
+        circle.current_pool = 100_000;
+        assert!(circle.locked_value().is_err());
+        assert!(circle.validate().is_err());
+    }
 
-        // Execute payout
-        let (recipient, amount) = circle.execute_payout(1234567902).unwrap();
-        assert_eq!(recipient, test_pubkey(1));
-        assert_eq!(amount, 200_000);
-        assert_eq!(circle.current_round, 1);
-        assert_eq!(circle.current_pool, 0);
+    #[test]
+    fn test_payout_needs_member_quorum() {
+        let app = test_app();
+        let mut funded = CircleState::new([1u8; 32], 100_000, 1_000, 1234567890, 2, 10);
+        for n in 1..=3 {
+            funded
+                .add_member(test_pubkey(n), n as u32 - 1, 1234567890)
+                .unwrap();
+        }
+        funded.start_circle(&test_pubkey(1), 1234568000).unwrap();
+        fund_round(&mut funded);
+        assert_eq!(funded.payout_approvals_needed(), 2);
 
-        circle.validate().unwrap();
+        // A single member, or an outsider, stamping a payout far in the future
+        // would push the next round past every honest contribution
+        let mut pushed = funded.clone();
+        pushed.execute_payout(4_000_000_000, None).unwrap();
+        let mut tx = transition_tx(&app, Some(&funded), &pushed);
+        pay(&mut tx, &test_pubkey(1), 300_000);
+        for approvals in [
+            vec![test_pubkey(9), test_pubkey(1)],
+            vec![test_pubkey(1)],
+            vec![test_pubkey(1), test_pubkey(1)],
+        ] {
+            let forged = CircleAction::Payout {
+                approvals,
+                timestamp: 4_000_000_000,
+                seed: None,
+            };
+            assert!(funded.clone().apply(&forged).is_err());
+            assert!(!app_contract(
+                &app,
+                &tx,
+                &at(4_000_000_000),
+                &unsigned(&forged)
+            ));
+        }
+        let forged = CircleAction::Payout {
+            approvals: vec![test_pubkey(1)],
+            timestamp: 4_000_000_000,
+            seed: None,
+        };
+        assert!(!app_contract(
+            &app,
+            &tx,
+            &at(4_000_000_000),
+            &signed(&funded, &forged)
+        ));
+
+        // Any majority attests the time with their signatures
+        for approvals in [
+            vec![test_pubkey(1), test_pubkey(2)],
+            vec![test_pubkey(3), test_pubkey(2)],
+        ] {
+            let payout = CircleAction::Payout {
+                approvals,
+                timestamp: funded.round_deadline(),
+                seed: None,
+            };
+            let mut paid = funded.clone();
+            paid.apply(&payout).unwrap();
+            let mut tx = transition_tx(&app, Some(&funded), &paid);
+            pay(&mut tx, &test_pubkey(1), 300_000);
+            assert!(app_contract(
+                &app,
+                &tx,
+                &at(funded.round_deadline()),
+                &signed(&funded, &payout)
+            ));
+            assert!(!app_contract(
+                &app,
+                &tx,
+                &at(funded.round_deadline()),
+                &unsigned(&payout)
+            ));
+        }
     }
 
     #[test]
-    fn test_state_transition_validation() {
-        let circle_id = [1u8; 32];
-        let mut state1 = CircleState::new(circle_id, 100_000, 2_592_000, 1234567890);
-        state1.add_member(test_pubkey(1), 0, 1234567890).unwrap();
-
-        let mut state2 = state1.clone();
-        state2.current_round = 1;
-        state2.current_pool = 0;
+    fn test_decodes_state_without_member_limits() {
+        let mut state = CircleState::new([1u8; 32], 100_000, 2_592_000, 1234567890, 3, 5);
+        state.add_member(test_pubkey(1), 0, 1234567890).unwrap();
 
-        // Valid transition
-        state1.validate_transition(&state2).unwrap();
+        // Encode the state as it was before membership limits were added
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&state, &mut bytes).unwrap();
+        let value: ciborium::Value = ciborium::de::from_reader(&bytes[..]).unwrap();
+        let ciborium::Value::Map(fields) = value else {
+            panic!("state should encode as a map");
+        };
+        let legacy = ciborium::Value::Map(
+            fields
+                .into_iter()
+                .filter(|(key, _)| {
+                    !matches!(key.as_text(), Some("min_members") | Some("max_members"))
+                })
+                .collect(),
+        );
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&legacy, &mut bytes).unwrap();
 
-        // Invalid transition - wrong circle ID
-        let mut state3 = state2.clone();
-        state3.circle_id = [2u8; 32];
-        assert!(state1.validate_transition(&state3).is_err());
+        let decoded: CircleState = ciborium::de::from_reader(&bytes[..]).unwrap();
+        assert_eq!((decoded.min_members, decoded.max_members), (2, 12));
     }
 }